@@ -0,0 +1,14 @@
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate bonzai_nbst;
+
+use bonzai_nbst::check;
+
+fuzz_target!(|data: &[u8]| {
+    // panic on divergence, so that libFuzzer records the input as a crash
+    if let Err(divergence) = check::fuzz::check_input(data) {
+        panic!("{}\n{}", divergence, divergence.tree);
+    }
+});
@@ -0,0 +1,162 @@
+use super::Workload;
+use super::output::Record;
+use super::registry::Backend;
+use super::stats::{self, RunConfig, Summary};
+
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Copy, Clone)]
+pub struct CompareConfig {
+    /// The relative slowdown of the median, as a fraction, above which a significant
+    /// change counts as a regression.
+    pub threshold: f64,
+    /// The significance level for the mann-whitney test.
+    pub alpha: f64,
+    /// Overrides for the warmup and repetitions recorded in the baseline.
+    pub warmup: Option<usize>,
+    pub repetitions: Option<usize>,
+}
+impl Default for CompareConfig {
+    fn default() -> Self {
+        CompareConfig {
+            threshold: 0.05,
+            alpha: 0.05,
+            warmup: None,
+            repetitions: None,
+        }
+    }
+}
+
+/// The samples for one backend on one workload, gathered from a results file.
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    pub backend: String,
+    pub workload: Workload,
+    pub warmup: usize,
+    pub samples: Vec<u64>,
+}
+
+/// Group records by backend and workload, in the order they first appear.
+pub fn baselines(records: Vec<Record>) -> Vec<Baseline> {
+    let mut baselines: Vec<Baseline> = Vec::new();
+    for record in records {
+        let found = baselines.iter()
+            .position(|b| b.backend == record.backend && b.workload == record.workload);
+        match found {
+            Some(i) => baselines[i].samples.push(record.ns),
+            None => baselines.push(Baseline {
+                backend: record.backend,
+                workload: record.workload,
+                warmup: record.warmup,
+                samples: vec![record.ns],
+            }),
+        };
+    }
+    baselines
+}
+
+pub struct Comparison {
+    pub backend: String,
+    pub workload: Workload,
+    pub baseline: Summary,
+    pub current: Summary,
+    /// Relative change of the median, positive meaning slower.
+    pub change: f64,
+    pub p_value: f64,
+    pub significant: bool,
+    pub regression: bool,
+}
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let verdict = match (self.regression, self.significant, self.change < 0.0) {
+            (true, _, _) => "REGRESSION",
+            (false, true, true) => "faster",
+            (false, true, false) => "slower, within threshold",
+            (false, false, _) => "no significant change",
+        };
+        write!(f, "{:<8} {:<10} {:>10} {:>16.0} {:>16.0} {:>+8.2}% {:>8.4}  {}",
+               self.backend, self.workload.name, self.workload.size, self.baseline.median,
+               self.current.median, self.change * 100.0, self.p_value, verdict)
+    }
+}
+
+pub const HEADER: &str = "\
+backend  workload         size  baseline median   current median   change        p  verdict";
+
+/// The comparisons made, and the baselines which couldn't be compared against.
+pub struct Report<'b> {
+    pub comparisons: Vec<Comparison>,
+    /// Baselines which had no backend to run them against.
+    pub unmatched: Vec<&'b Baseline>,
+    /// Baselines with a median of 0 ns, which no change can be measured relative to.
+    pub zero_median: Vec<&'b Baseline>,
+}
+
+/// Re-run every baseline whose backend is among `backends`, and compare the new samples
+/// against the old ones.
+pub fn compare<'b>(baselines: &'b [Baseline], backends: &[Box<dyn Backend>], config: &CompareConfig)
+    -> Result<Report<'b>, String> {
+
+    let mut comparisons = Vec::new();
+    let mut unmatched = Vec::new();
+    let mut zero_median = Vec::new();
+    for baseline in baselines {
+        let backend = match backends.iter().find(|b| b.name() == baseline.backend) {
+            Some(backend) => backend,
+            None => {
+                unmatched.push(baseline);
+                continue;
+            },
+        };
+        let baseline_summary = Summary::from_samples(baseline.samples.clone());
+        if baseline_summary.median == 0.0 {
+            zero_median.push(baseline);
+            continue;
+        }
+        let ops = baseline.workload.ops()?;
+        let run = RunConfig {
+            warmup: config.warmup.unwrap_or(baseline.warmup),
+            repetitions: config.repetitions.unwrap_or_else(|| baseline.samples.len()),
+        };
+        let current = backend.run(&ops, &run);
+
+        let change = (current.median - baseline_summary.median) / baseline_summary.median;
+        let p_value = stats::mann_whitney_p(&baseline.samples, &current.samples);
+        let significant = p_value < config.alpha;
+        comparisons.push(Comparison {
+            backend: baseline.backend.clone(),
+            workload: baseline.workload.clone(),
+            significant,
+            regression: significant && change > config.threshold,
+            baseline: baseline_summary,
+            current,
+            change,
+            p_value,
+        });
+    }
+    Ok(Report {
+        comparisons,
+        unmatched,
+        zero_median,
+    })
+}
+
+#[test]
+fn zero_baselines_are_not_compared() {
+    use super::registry;
+
+    let baseline = |backend: &str, samples: Vec<u64>| Baseline {
+        backend: backend.to_owned(),
+        workload: Workload::rand_ops(100, 0),
+        warmup: 0,
+        samples,
+    };
+    let baselines = vec![baseline("boxy", vec![0, 0, 5]), baseline("btree", vec![900, 1000, 1100]),
+                         baseline("avl", vec![1000])];
+    let report = compare(&baselines, &registry::registry(), &CompareConfig::default()).unwrap();
+    assert_eq!(report.comparisons.len(), 1);
+    assert_eq!(report.comparisons[0].backend, "btree");
+    assert!(report.comparisons[0].change.is_finite());
+    assert_eq!(report.zero_median.iter().map(|b| &b.backend[..]).collect::<Vec<_>>(), ["boxy"]);
+    assert_eq!(report.unmatched.iter().map(|b| &b.backend[..]).collect::<Vec<_>>(), ["avl"]);
+}
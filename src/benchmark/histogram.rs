@@ -0,0 +1,201 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+// each power of two is split into 2^SUB_BITS linear sub-buckets, which bounds the relative
+// error of any reported value to 1/2^SUB_BITS
+const SUB_BITS: u32 = 4;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+const NUM_BUCKETS: usize = (64 - SUB_BITS as usize + 1) * SUB_BUCKETS;
+
+/// A histogram of nanosecond latencies, with logarithmically sized buckets.
+///
+/// Recording is a couple of bit operations and an increment, so it can sit inside a timing
+/// loop without dominating what it measures.
+#[derive(Clone)]
+pub struct LogHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: u128,
+    min: u64,
+    max: u64,
+}
+impl LogHistogram {
+    pub fn new() -> Self {
+        LogHistogram {
+            buckets: vec![0; NUM_BUCKETS],
+            count: 0,
+            sum: 0,
+            min: u64::max_value(),
+            max: 0,
+        }
+    }
+
+    pub fn record(&mut self, ns: u64) {
+        self.buckets[bucket_of(ns)] += 1;
+        self.count += 1;
+        self.sum += ns as u128;
+        self.min = self.min.min(ns);
+        self.max = self.max.max(ns);
+    }
+
+    pub fn record_duration(&mut self, duration: Duration) {
+        self.record(duration.as_nanos() as u64);
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(self.sum as f64 / self.count as f64)
+        } else {
+            None
+        }
+    }
+
+    /// The upper bound of the bucket containing the p-th percentile, clamped to the
+    /// observed maximum.
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((p / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Some(bucket_high(i).min(self.max));
+            }
+        }
+        unreachable!("histogram count out of sync with buckets")
+    }
+
+    /// Counts per power of two, as `(lower bound inclusive, upper bound exclusive, count)`,
+    /// skipping empty ranges.
+    pub fn octaves(&self) -> Vec<(u64, u64, u64)> {
+        self.buckets.chunks(SUB_BUCKETS)
+            .enumerate()
+            .map(|(octave, chunk)| (
+                bucket_low(octave * SUB_BUCKETS),
+                bucket_high(octave * SUB_BUCKETS + SUB_BUCKETS - 1).saturating_add(1),
+                chunk.iter().sum::<u64>(),
+            ))
+            .filter(|&(_, _, n)| n > 0)
+            .collect()
+    }
+}
+impl Default for LogHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Display for LogHistogram {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.count == 0 {
+            return write!(f, "        (no samples)");
+        }
+        writeln!(f, "        count {}, mean {:.0} ns, min {} ns, max {} ns",
+                 self.count, self.mean().unwrap(), self.min, self.max)?;
+        writeln!(f, "        p50 {} ns, p90 {} ns, p99 {} ns, p99.9 {} ns",
+                 self.percentile(50.0).unwrap(), self.percentile(90.0).unwrap(),
+                 self.percentile(99.0).unwrap(), self.percentile(99.9).unwrap())?;
+        let octaves = self.octaves();
+        let widest = octaves.iter().map(|&(_, _, n)| n).max().unwrap();
+        for (i, &(low, high, n)) in octaves.iter().enumerate() {
+            let marks = "#".repeat(((n * 40 + widest - 1) / widest) as usize);
+            write!(f, "        {:>10} .. {:<10} ns {:>10} {}", low, high, n, marks)?;
+            if i + 1 < octaves.len() {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn bucket_of(ns: u64) -> usize {
+    if ns < SUB_BUCKETS as u64 {
+        // the first octave is exact
+        ns as usize
+    } else {
+        let magnitude = 63 - ns.leading_zeros();
+        let shift = magnitude - SUB_BITS;
+        let sub = (ns >> shift) as usize - SUB_BUCKETS;
+        (shift as usize + 1) * SUB_BUCKETS + sub
+    }
+}
+
+fn bucket_low(bucket: usize) -> u64 {
+    let (octave, sub) = (bucket / SUB_BUCKETS, (bucket % SUB_BUCKETS) as u64);
+    if octave == 0 {
+        sub
+    } else {
+        (SUB_BUCKETS as u64 + sub) << (octave - 1)
+    }
+}
+
+fn bucket_high(bucket: usize) -> u64 {
+    if bucket + 1 >= NUM_BUCKETS {
+        u64::max_value()
+    } else {
+        bucket_low(bucket + 1) - 1
+    }
+}
+
+/// One latency histogram per kind of op.
+#[derive(Clone)]
+pub struct OpLatencies {
+    pub insert: LogHistogram,
+    pub remove: LogHistogram,
+    pub contains: LogHistogram,
+}
+impl OpLatencies {
+    pub fn new() -> Self {
+        OpLatencies {
+            insert: LogHistogram::new(),
+            remove: LogHistogram::new(),
+            contains: LogHistogram::new(),
+        }
+    }
+}
+impl Default for OpLatencies {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Display for OpLatencies {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "    insert:")?;
+        writeln!(f, "{}", self.insert)?;
+        writeln!(f, "    remove:")?;
+        writeln!(f, "{}", self.remove)?;
+        writeln!(f, "    contains:")?;
+        write!(f, "{}", self.contains)
+    }
+}
+
+#[test]
+fn buckets_bound_their_values() {
+    for ns in (0..5000).chain((0..64).map(|shift| 1u64 << shift)).chain(vec![u64::max_value()]) {
+        let bucket = bucket_of(ns);
+        assert!(bucket < NUM_BUCKETS);
+        assert!(bucket_low(bucket) <= ns && ns <= bucket_high(bucket), "{} in bucket {}", ns, bucket);
+        // values are only rounded within a sub-bucket, never by more than 1/2^SUB_BITS
+        assert!((bucket_high(bucket) - bucket_low(bucket)) as f64 <= ns as f64 / SUB_BUCKETS as f64);
+    }
+    for ns in 0..SUB_BUCKETS as u64 {
+        assert_eq!((bucket_low(bucket_of(ns)), bucket_high(bucket_of(ns))), (ns, ns));
+    }
+}
+
+#[test]
+fn percentiles_come_from_buckets() {
+    let mut histogram = LogHistogram::new();
+    assert_eq!((histogram.percentile(50.0), histogram.mean()), (None, None));
+    for ns in 1..101 {
+        histogram.record(ns);
+    }
+    assert_eq!(histogram.mean(), Some(50.5));
+    assert_eq!(histogram.percentile(0.0), Some(1));
+    // 50 lands in the bucket 50..=51
+    assert_eq!(histogram.percentile(50.0), Some(51));
+    // 100 lands in the bucket 100..=103, which is clamped to the largest value seen
+    assert_eq!(histogram.percentile(100.0), Some(100));
+    assert_eq!(&histogram.octaves()[..2], &[(0, 16, 15), (16, 32, 16)]);
+}
@@ -0,0 +1,164 @@
+use bst::Bst;
+use super::Op;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// counting is switched off outside of `measure`, so that the timed runs only pay for
+// one relaxed load per allocation
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator which defers to the system allocator, and counts what passes
+/// through it while a `measure` is in progress.
+pub struct TrackingAlloc;
+
+unsafe impl GlobalAlloc for TrackingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() && ENABLED.load(Ordering::Relaxed) {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() && ENABLED.load(Ordering::Relaxed) {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        if ENABLED.load(Ordering::Relaxed) {
+            record_dealloc(layout.size());
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() && ENABLED.load(Ordering::Relaxed) {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED.fetch_add(size, Ordering::Relaxed);
+    let live = LIVE.fetch_add(size, Ordering::Relaxed) + size;
+    let mut peak = PEAK.load(Ordering::Relaxed);
+    while live > peak {
+        match PEAK.compare_exchange_weak(peak, live, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(actual) => peak = actual,
+        };
+    }
+}
+
+fn record_dealloc(size: usize) {
+    // frees of memory allocated before counting began would otherwise underflow
+    let mut live = LIVE.load(Ordering::Relaxed);
+    loop {
+        let new_live = live.saturating_sub(size);
+        match LIVE.compare_exchange_weak(live, new_live, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(actual) => live = actual,
+        };
+    }
+}
+
+/// Heap usage while applying a workload to a fresh tree.
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryUsage {
+    /// Calls to alloc or realloc.
+    pub allocations: usize,
+    /// Total bytes requested, including memory later freed.
+    pub bytes_allocated: usize,
+    /// The high water mark of live bytes.
+    pub peak_bytes: usize,
+    /// Bytes still live once every op has been applied, which is the tree's footprint.
+    pub live_bytes: usize,
+    /// Elements in the tree once every op has been applied.
+    pub elements: usize,
+}
+impl MemoryUsage {
+    pub fn bytes_per_element(&self) -> Option<f64> {
+        if self.elements > 0 {
+            Some(self.live_bytes as f64 / self.elements as f64)
+        } else {
+            None
+        }
+    }
+}
+impl Display for MemoryUsage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "    memory   {} bytes live, {} peak, {} allocated over {} allocations",
+               self.live_bytes, self.peak_bytes, self.bytes_allocated, self.allocations)?;
+        match self.bytes_per_element() {
+            Some(per) => write!(f, "\n             {} elements, {:.1} bytes/element",
+                                self.elements, per),
+            None => write!(f, "\n             0 elements"),
+        }
+    }
+}
+
+/// Run `work` with counting switched on, returning its result along with what it
+/// allocated, with no elements counted. As with `measure`, the counts are only meaningful
+/// if `TrackingAlloc` is the global allocator and nothing else allocates concurrently.
+pub fn track<R, F: FnOnce() -> R>(work: F) -> (R, MemoryUsage) {
+    LIVE.store(0, Ordering::SeqCst);
+    PEAK.store(0, Ordering::SeqCst);
+    ALLOCATIONS.store(0, Ordering::SeqCst);
+    ALLOCATED.store(0, Ordering::SeqCst);
+    ENABLED.store(true, Ordering::SeqCst);
+    let result = work();
+    ENABLED.store(false, Ordering::SeqCst);
+    (result, MemoryUsage {
+        allocations: ALLOCATIONS.load(Ordering::SeqCst),
+        bytes_allocated: ALLOCATED.load(Ordering::SeqCst),
+        peak_bytes: PEAK.load(Ordering::SeqCst),
+        live_bytes: LIVE.load(Ordering::SeqCst),
+        elements: 0,
+    })
+}
+
+/// Apply the ops to a fresh tree, counting allocations. The counts are only meaningful
+/// if `TrackingAlloc` is installed as the global allocator, and if nothing else is
+/// allocating concurrently.
+pub fn measure<T: Ord + Debug + Clone, B: Bst<T>>(ops: &[Op<T>]) -> MemoryUsage
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    let (tree, usage) = track(|| {
+        let mut tree = B::new();
+        for op in ops {
+            match *op {
+                Op::Insert(ref t) => {
+                    tree.insert(t.clone());
+                },
+                Op::Remove(ref t) => {
+                    tree.remove(t);
+                },
+                Op::Contains(ref t) => {
+                    tree.contains(t);
+                }
+            };
+        }
+        tree
+    });
+    let usage = MemoryUsage {
+        elements: tree.into_iter().count(),
+        ..usage
+    };
+    mem::drop(tree);
+    usage
+}
@@ -0,0 +1,244 @@
+
+pub mod stats;
+pub mod histogram;
+pub mod registry;
+pub mod output;
+pub mod compare;
+pub mod sweep;
+pub mod memory;
+pub mod threaded;
+
+use bst::Bst;
+
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::time::Instant;
+
+use rand::prelude::*;
+use rand::distributions::{Distribution, Standard};
+use rand::XorShiftRng;
+use stopwatch::Stopwatch;
+
+use self::histogram::OpLatencies;
+
+#[derive(Debug, Copy, Clone)]
+pub enum Op<T> {
+    Insert(T),
+    Remove(T),
+    Contains(T),
+}
+impl<T> Op<T> {
+    pub fn key(&self) -> &T {
+        match self {
+            &Op::Insert(ref t) | &Op::Remove(ref t) | &Op::Contains(ref t) => t,
+        }
+    }
+}
+
+pub fn first_after<T: Clone + Ord>(set: &BTreeSet<T>, elem: T) -> Option<T> {
+    set.range(..elem).next_back().cloned()
+}
+
+/// The seed which `rand_ops` used before it was parameterized.
+pub const DEFAULT_SEED: u64 = 0;
+
+/// Expand a 64-bit seed into an rng. Seed 0 expands to the original fixed seed bytes
+/// `[0, 1, .., 15]`, and no seed expands to the all-zero state which xorshift rejects.
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = ((seed >> (8 * (i % 8))) as u8).wrapping_add(i as u8);
+    }
+    XorShiftRng::from_seed(bytes)
+}
+
+pub fn rand_ops<T: Clone + Ord>(num_ops: usize, seed: u64) -> Vec<Op<T>>
+    where Standard: Distribution<T> {
+
+    let mut rng = seeded_rng(seed);
+
+    let mut contains = BTreeSet::new();
+
+    let mut ops = Vec::new();
+    for _ in 0..num_ops {
+        match rng.gen::<u8>() % 7 {
+            0 | 6 => {
+                // insert rand elem
+                let t: T = rng.gen();
+                ops.push(Op::Insert(t.clone()));
+                contains.insert(t);
+            },
+            1 => {
+                // remove rand elem
+                let t: T = rng.gen();
+                ops.push(Op::Remove(t.clone()));
+                contains.remove(&t);
+            },
+            2 => {
+                // check contains rand elem
+                let t: T = rng.gen();
+                ops.push(Op::Contains(t));
+            }
+            3 => {
+                // insert existent elem
+                if contains.is_empty() {
+                    continue;
+                }
+                let t: Option<T> = first_after(&contains, rng.gen::<T>());
+                if let Some(t) = t {
+                    ops.push(Op::Insert(t.clone()));
+                    contains.insert(t);
+                }
+            },
+            4 => {
+                // remove existent elem
+                if contains.is_empty() {
+                    continue;
+                }
+                let t: Option<T> = first_after(&contains, rng.gen::<T>());
+                if let Some(t) = t {
+                    ops.push(Op::Remove(t.clone()));
+                    contains.remove(&t);
+                }
+            },
+            5 => {
+                // check contains existent elem
+                if contains.is_empty() {
+                    continue;
+                }
+                let t: Option<T> = first_after(&contains, rng.gen::<T>());
+                if let Some(t) = t {
+                    ops.push(Op::Insert(t.clone()));
+                    ops.push(Op::Contains(t));
+                }
+            },
+            _ => unreachable!()
+        };
+    }
+
+    ops
+}
+
+/// A reproducible description of an op sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workload {
+    pub name: String,
+    pub size: usize,
+    pub seed: u64,
+}
+impl Workload {
+    pub fn rand_ops(size: usize, seed: u64) -> Self {
+        Workload {
+            name: "rand_ops".to_owned(),
+            size,
+            seed,
+        }
+    }
+
+    /// Regenerate the ops this workload describes.
+    pub fn ops(&self) -> Result<Vec<Op<i32>>, String> {
+        match self.name.as_str() {
+            "rand_ops" => Ok(rand_ops(self.size, self.seed)),
+            other => Err(format!("unknown workload: {}", other)),
+        }
+    }
+}
+
+/// How many of each kind of op a sequence contains.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OpCounts {
+    pub inserts: usize,
+    pub removes: usize,
+    pub contains: usize,
+}
+impl OpCounts {
+    pub fn of<T>(ops: &[Op<T>]) -> Self {
+        let mut counts = OpCounts {
+            inserts: 0,
+            removes: 0,
+            contains: 0,
+        };
+        for op in ops {
+            match *op {
+                Op::Insert(_) => counts.inserts += 1,
+                Op::Remove(_) => counts.removes += 1,
+                Op::Contains(_) => counts.contains += 1,
+            };
+        }
+        counts
+    }
+
+    pub fn total(&self) -> usize {
+        self.inserts + self.removes + self.contains
+    }
+}
+
+fn apply_ops<T: Ord + Debug, B: Bst<T>>(ops: Vec<Op<T>>) -> B
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    let mut tree = B::new();
+    for op in ops {
+        match op {
+            Op::Insert(t) => {
+                tree.insert(t);
+            },
+            Op::Remove(t) => {
+                tree.remove(&t);
+            },
+            Op::Contains(t) => {
+                tree.contains(&t);
+            }
+        };
+    }
+    tree
+}
+
+/// The result of a single instrumented run.
+pub struct Timing {
+    pub total_ms: f64,
+    pub latencies: OpLatencies,
+}
+
+/// Time a single run, also recording the latency of every op into a histogram for its kind.
+///
+/// The total includes the cost of reading the clock around each op, so it should not be
+/// compared against the totals from `time_ns`.
+pub fn time_ms<T: Ord + Debug, B: Bst<T>>(ops: Vec<Op<T>>) -> Timing
+    where for<'s> &'s B: IntoIterator<Item = &'s T>{
+
+    let mut latencies = OpLatencies::new();
+    let timer = Stopwatch::start_new();
+    let mut tree = B::new();
+    for op in ops {
+        match op {
+            Op::Insert(t) => {
+                let start = Instant::now();
+                tree.insert(t);
+                latencies.insert.record_duration(start.elapsed());
+            },
+            Op::Remove(t) => {
+                let start = Instant::now();
+                tree.remove(&t);
+                latencies.remove.record_duration(start.elapsed());
+            },
+            Op::Contains(t) => {
+                let start = Instant::now();
+                tree.contains(&t);
+                latencies.contains.record_duration(start.elapsed());
+            }
+        };
+    }
+    Timing {
+        total_ms: timer.elapsed().as_nanos() as f64 / 1e6,
+        latencies,
+    }
+}
+
+pub fn time_ns<T: Ord + Debug, B: Bst<T>>(ops: Vec<Op<T>>) -> u64
+    where for<'s> &'s B: IntoIterator<Item = &'s T>{
+
+    let timer = Stopwatch::start_new();
+    let _tree = apply_ops::<T, B>(ops);
+    // the tree is dropped after the timer is read
+    timer.elapsed().as_nanos() as u64
+}
@@ -0,0 +1,313 @@
+use super::{Workload, OpCounts};
+use super::stats::{RunConfig, Summary};
+use super::memory::MemoryUsage;
+use bst::shape::TreeStats;
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde_json::{self, Number, Value as Json};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            other => Err(format!("unknown format: {} (expected json, csv or table)", other)),
+        }
+    }
+}
+
+/// Every repetition of one backend running one workload.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub backend: String,
+    pub workload: Workload,
+    pub counts: OpCounts,
+    pub config: RunConfig,
+    pub summary: Summary,
+    pub memory: Option<MemoryUsage>,
+    pub shape: Option<TreeStats>,
+}
+impl BenchResult {
+    pub fn records(&self) -> Vec<Record> {
+        self.summary.samples.iter()
+            .enumerate()
+            .map(|(rep, &ns)| Record {
+                backend: self.backend.clone(),
+                workload: self.workload.clone(),
+                counts: self.counts,
+                warmup: self.config.warmup,
+                rep,
+                ns,
+                outlier: self.summary.outliers.contains(&rep),
+                memory: self.memory,
+                shape: self.shape.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A single timed repetition, which is the unit of machine-readable output.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub backend: String,
+    pub workload: Workload,
+    pub counts: OpCounts,
+    pub warmup: usize,
+    pub rep: usize,
+    pub ns: u64,
+    pub outlier: bool,
+    /// Measured once per backend and workload, so repeated in each of its records.
+    pub memory: Option<MemoryUsage>,
+    pub shape: Option<TreeStats>,
+}
+impl Record {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![
+            ("backend", Value::Str(self.backend.clone())),
+            ("workload", Value::Str(self.workload.name.clone())),
+            ("size", Value::Int(self.workload.size as u64)),
+            ("seed", Value::Int(self.workload.seed)),
+            ("ops", Value::Int(self.counts.total() as u64)),
+            ("inserts", Value::Int(self.counts.inserts as u64)),
+            ("removes", Value::Int(self.counts.removes as u64)),
+            ("contains", Value::Int(self.counts.contains as u64)),
+            ("warmup", Value::Int(self.warmup as u64)),
+            ("rep", Value::Int(self.rep as u64)),
+            ("ns", Value::Int(self.ns)),
+            ("outlier", Value::Bool(self.outlier)),
+            ("live_bytes", self.memory.map(|m| Value::Int(m.live_bytes as u64))
+                .unwrap_or(Value::Null)),
+            ("peak_bytes", self.memory.map(|m| Value::Int(m.peak_bytes as u64))
+                .unwrap_or(Value::Null)),
+            ("allocations", self.memory.map(|m| Value::Int(m.allocations as u64))
+                .unwrap_or(Value::Null)),
+            ("elements", self.memory.map(|m| Value::Int(m.elements as u64))
+                .unwrap_or(Value::Null)),
+            ("bytes_per_element", self.memory.and_then(|m| m.bytes_per_element())
+                .map(Value::Float)
+                .unwrap_or(Value::Null)),
+            ("height", self.shape.as_ref().map(|s| Value::Int(s.height as u64))
+                .unwrap_or(Value::Null)),
+            ("avg_depth", self.shape.as_ref().map(|s| Value::Float(s.avg_depth))
+                .unwrap_or(Value::Null)),
+            ("leaves", self.shape.as_ref().map(|s| Value::Int(s.leaves as u64))
+                .unwrap_or(Value::Null)),
+            ("one_child", self.shape.as_ref().map(|s| Value::Int(s.one_child as u64))
+                .unwrap_or(Value::Null)),
+            ("two_children", self.shape.as_ref().map(|s| Value::Int(s.two_children as u64))
+                .unwrap_or(Value::Null)),
+            ("worst_imbalance", self.shape.as_ref()
+                .map(|s| Value::Int(s.worst_imbalance as u64))
+                .unwrap_or(Value::Null)),
+        ]
+    }
+
+    fn to_json(&self) -> Json {
+        Json::Object(self.fields().into_iter()
+            .map(|(name, value)| (name.to_owned(), value.json()))
+            .collect())
+    }
+
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let str_field = |name: &str| json.get(name)
+            .and_then(Json::as_str)
+            .map(|s| s.to_owned())
+            .ok_or_else(|| format!("record missing string field {:?}", name));
+        let int_field = |name: &str| json.get(name)
+            .and_then(Json::as_u64)
+            .ok_or_else(|| format!("record missing integer field {:?}", name));
+        Ok(Record {
+            backend: str_field("backend")?,
+            workload: Workload {
+                name: str_field("workload")?,
+                size: int_field("size")? as usize,
+                seed: int_field("seed")?,
+            },
+            counts: OpCounts {
+                inserts: int_field("inserts")? as usize,
+                removes: int_field("removes")? as usize,
+                contains: int_field("contains")? as usize,
+            },
+            warmup: int_field("warmup")? as usize,
+            rep: int_field("rep")? as usize,
+            ns: int_field("ns")?,
+            outlier: json.get("outlier").and_then(Json::as_bool).unwrap_or(false),
+            memory: None,
+            shape: None,
+        })
+    }
+}
+
+/// Read back the records written by the json format.
+pub fn read_json(text: &str) -> Result<Vec<Record>, String> {
+    match serde_json::from_str(text).map_err(|e| format!("invalid json: {}", e))? {
+        Json::Array(elems) => elems.iter().map(Record::from_json).collect(),
+        _ => Err("expected a json array of records".to_owned()),
+    }
+}
+
+enum Value {
+    Str(String),
+    Int(u64),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+impl Value {
+    fn json(&self) -> Json {
+        match *self {
+            Value::Str(ref s) => Json::String(s.clone()),
+            Value::Int(n) => Json::from(n),
+            // json has no nan or infinity
+            Value::Float(x) => Number::from_f64(x).map(Json::Number).unwrap_or(Json::Null),
+            Value::Bool(b) => Json::Bool(b),
+            Value::Null => Json::Null,
+        }
+    }
+
+    fn csv(&self) -> String {
+        match *self {
+            Value::Str(ref s) => {
+                if s.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+                    format!("\"{}\"", s.replace('"', "\"\""))
+                } else {
+                    s.clone()
+                }
+            },
+            Value::Int(n) => n.to_string(),
+            Value::Float(x) => format!("{:.3}", x),
+            Value::Bool(b) => b.to_string(),
+            Value::Null => String::new(),
+        }
+    }
+}
+
+pub fn write<W: Write>(out: &mut W, format: Format, results: &[BenchResult]) -> io::Result<()> {
+    match format {
+        Format::Table => write_table(out, results),
+        Format::Json => write_json(out, results),
+        Format::Csv => write_csv(out, results),
+    }
+}
+
+fn write_table<W: Write>(out: &mut W, results: &[BenchResult]) -> io::Result<()> {
+    for result in results {
+        writeln!(out, "{} on {} (size {}, seed {}, {} ops: {} insert, {} remove, {} contains)",
+                 result.backend, result.workload.name, result.workload.size,
+                 result.workload.seed, result.counts.total(), result.counts.inserts,
+                 result.counts.removes, result.counts.contains)?;
+        writeln!(out, "  {} reps, {} warmup:", result.config.repetitions, result.config.warmup)?;
+        writeln!(out, "{}", result.summary)?;
+        if let Some(ref memory) = result.memory {
+            writeln!(out, "{}", memory)?;
+        }
+        if let Some(ref shape) = result.shape {
+            writeln!(out, "{}", shape)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(out: &mut W, results: &[BenchResult]) -> io::Result<()> {
+    let records: Vec<Json> = results.iter()
+        .flat_map(|result| result.records())
+        .map(|record| record.to_json())
+        .collect();
+    serde_json::to_writer(&mut *out, &records)?;
+    writeln!(out)
+}
+
+fn write_csv<W: Write>(out: &mut W, results: &[BenchResult]) -> io::Result<()> {
+    let records: Vec<Record> = results.iter()
+        .flat_map(|result| result.records())
+        .collect();
+    if let Some(first) = records.first() {
+        let header: Vec<&str> = first.fields().iter()
+            .map(|&(name, _)| name)
+            .collect();
+        writeln!(out, "{}", header.join(","))?;
+    }
+    for record in &records {
+        let row: Vec<String> = record.fields().iter()
+            .map(|&(_, ref value)| value.csv())
+            .collect();
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+#[test]
+fn csv_quotes_fields() {
+    let csv = |s: &str| Value::Str(s.to_owned()).csv();
+    assert_eq!(csv("bonzai"), "bonzai");
+    assert_eq!(csv("a,b"), "\"a,b\"");
+    assert_eq!(csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(csv("two\nlines"), "\"two\nlines\"");
+    assert_eq!(csv("two\r\nlines"), "\"two\r\nlines\"");
+    assert_eq!(Value::Null.csv(), "");
+    assert_eq!(Value::Float(1.23456).csv(), "1.235");
+}
+
+#[test]
+fn reads_back_written_results() {
+    let result = BenchResult {
+        backend: "quoted \"back\\slash\"\n".to_owned(),
+        workload: Workload::rand_ops(1000, 7),
+        counts: OpCounts {
+            inserts: 300,
+            removes: 200,
+            contains: 500,
+        },
+        config: RunConfig {
+            warmup: 2,
+            repetitions: 3,
+        },
+        summary: Summary::from_samples(vec![1200, 1100, 90000]),
+        memory: None,
+        shape: None,
+    };
+    let mut out = Vec::new();
+    write(&mut out, Format::Json, ::std::slice::from_ref(&result)).unwrap();
+    let records = read_json(&String::from_utf8(out).unwrap()).unwrap();
+
+    assert_eq!(records.len(), 3);
+    for (rep, record) in records.iter().enumerate() {
+        assert_eq!(record.backend, result.backend);
+        assert_eq!(record.workload, result.workload);
+        assert_eq!(record.counts, result.counts);
+        assert_eq!(record.warmup, 2);
+        assert_eq!(record.rep, rep);
+        assert_eq!(record.ns, result.summary.samples[rep]);
+    }
+}
+
+#[test]
+fn rejects_malformed_records() {
+    let record = "{\"backend\": \"boxy\", \"workload\": \"rand_ops\", \"size\": 10, \
+                  \"seed\": 0, \"inserts\": 1, \"removes\": 1, \"contains\": 1, \
+                  \"warmup\": 0, \"rep\": 0, \"ns\": NS}";
+    assert!(read_json(&format!("[{}]", record.replace("NS", "120"))).is_ok());
+    assert!(read_json(&format!("[{}]", record.replace("NS", "1-2"))).unwrap_err()
+        .starts_with("invalid json"));
+    assert_eq!(read_json(&format!("[{}]", record.replace("NS", "1.5"))).unwrap_err(),
+               "record missing integer field \"ns\"");
+    assert!(read_json("{}").is_err());
+}
+
+#[test]
+fn non_finite_floats_are_null() {
+    assert_eq!(Value::Float(::std::f64::NAN).json(), Json::Null);
+    assert_eq!(Value::Float(::std::f64::INFINITY).json(), Json::Null);
+    assert_eq!(Value::Float(0.5).json(), Json::from(0.5));
+}
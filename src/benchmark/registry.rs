@@ -0,0 +1,142 @@
+use bst::Bst;
+use bst::shape::TreeStats;
+use bst::dot::Annotation;
+use bst::bonzai::BonzaiBst;
+use bst::boxy::BoxBst;
+use bst::persistent::PersistentBst;
+use bst::stdlib::BTreeSet;
+use super::{Op, Timing, time_ms, apply_ops};
+use super::stats::{self, RunConfig, Summary};
+use super::threaded::{self, Sharing};
+use super::memory::{self, MemoryUsage};
+
+use std::marker::PhantomData;
+
+/// A benchmarkable `Bst` implementation, with the element type fixed to `i32`.
+///
+/// `Bst` itself can't be made into a trait object, so each implementation is registered
+/// through a `Registered<B>`, which monomorphizes the benchmark functions for `B`.
+pub trait Backend {
+    fn name(&self) -> &'static str;
+
+    fn run(&self, ops: &[Op<i32>], config: &RunConfig) -> Summary;
+
+    /// Run each stream on its own thread, all against one shared tree.
+    fn run_threaded(&self, streams: &[Vec<Op<i32>>], sharing: Sharing, config: &RunConfig)
+        -> Summary;
+
+    fn time_ms(&self, ops: &[Op<i32>]) -> Timing;
+
+    fn memory(&self, ops: &[Op<i32>]) -> MemoryUsage;
+
+    /// The shape of the tree once every op has been applied.
+    fn shape(&self, ops: &[Op<i32>]) -> Option<TreeStats>;
+
+    /// A graphviz digraph of the tree once every op has been applied.
+    fn dot(&self, ops: &[Op<i32>], annotation: Option<Annotation>) -> Option<String>;
+}
+
+struct Registered<B> {
+    name: &'static str,
+    _tree: PhantomData<fn() -> B>,
+}
+impl<B: Bst<i32> + Send + Sync + 'static> Backend for Registered<B>
+    where for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn run(&self, ops: &[Op<i32>], config: &RunConfig) -> Summary {
+        stats::run::<i32, B>(ops, config)
+    }
+
+    fn run_threaded(&self, streams: &[Vec<Op<i32>>], sharing: Sharing, config: &RunConfig)
+        -> Summary {
+
+        threaded::run::<B>(streams, sharing, config)
+    }
+
+    fn time_ms(&self, ops: &[Op<i32>]) -> Timing {
+        time_ms::<i32, B>(ops.to_vec())
+    }
+
+    fn memory(&self, ops: &[Op<i32>]) -> MemoryUsage {
+        memory::measure::<i32, B>(ops)
+    }
+
+    fn shape(&self, ops: &[Op<i32>]) -> Option<TreeStats> {
+        apply_ops::<i32, B>(ops.to_vec()).stats()
+    }
+
+    fn dot(&self, ops: &[Op<i32>], annotation: Option<Annotation>) -> Option<String> {
+        apply_ops::<i32, B>(ops.to_vec()).to_dot(annotation)
+    }
+}
+
+pub fn register<B: Bst<i32> + Send + Sync + 'static>(name: &'static str) -> Box<dyn Backend>
+    where for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    Box::new(Registered::<B> {
+        name,
+        _tree: PhantomData,
+    })
+}
+
+/// Every backend, in the order they're benchmarked by default.
+pub fn registry() -> Vec<Box<dyn Backend>> {
+    vec![
+        register::<BonzaiBst<i32>>("bonzai"),
+        register::<BoxBst<i32>>("boxy"),
+        register::<PersistentBst<i32>>("persistent"),
+        register::<BTreeSet<i32>>("btree"),
+    ]
+}
+
+/// Take the items with the given names out of `available`, in the order given. Each item can
+/// be selected at most once.
+pub fn select_by_name<T>(mut available: Vec<T>, names: &[String], name: fn(&T) -> &'static str)
+    -> Result<Vec<T>, String> {
+
+    let known: Vec<&str> = available.iter().map(name).collect();
+    let mut selected = Vec::new();
+    for wanted in names {
+        match available.iter().position(|item| name(item) == wanted.as_str()) {
+            Some(i) => selected.push(available.remove(i)),
+            None => return Err(format!("unknown or repeated backend: {} (known: {})",
+                                       wanted, known.join(", "))),
+        };
+    }
+    Ok(selected)
+}
+
+/// Look up backends by name, in the given order.
+pub fn select(names: &[String]) -> Result<Vec<Box<dyn Backend>>, String> {
+    select_by_name(registry(), names, |backend| backend.name())
+}
+
+#[test]
+fn select_by_name_looks_up_in_order() {
+    let names = |names: &[&str]| names.iter().map(|&name| name.to_owned()).collect::<Vec<_>>();
+    let available = || vec!["bonzai", "boxy", "btree"];
+    let selected = select_by_name(available(), &names(&["btree", "bonzai"]), |&name| name).unwrap();
+    assert_eq!(selected, ["btree", "bonzai"]);
+
+    let e = select_by_name(available(), &names(&["boxy", "avl"]), |&name| name).err().unwrap();
+    assert!(e.contains("avl") && e.contains("bonzai, boxy, btree"), "{}", e);
+    assert!(select_by_name(available(), &names(&["boxy", "boxy"]), |&name| name).is_err());
+}
+
+#[test]
+fn registered_backends_run_the_same_ops() {
+    let ops = vec![Op::Insert(3), Op::Insert(1), Op::Insert(2), Op::Remove(3), Op::Contains(1)];
+    for backend in registry() {
+        // only btree doesn't expose its shape
+        match backend.shape(&ops) {
+            Some(stats) => assert_eq!(stats.nodes, 2, "{}", backend.name()),
+            None => assert_eq!(backend.name(), "btree"),
+        };
+        let summary = backend.run(&ops, &RunConfig { warmup: 0, repetitions: 2 });
+        assert_eq!(summary.samples.len(), 2);
+    }
+}
@@ -0,0 +1,235 @@
+use bst::Bst;
+use super::{Op, time_ns};
+
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// How many untimed warmup runs, and how many timed repetitions, to perform.
+#[derive(Debug, Copy, Clone)]
+pub struct RunConfig {
+    pub warmup: usize,
+    pub repetitions: usize,
+}
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            warmup: 3,
+            repetitions: 10,
+        }
+    }
+}
+
+/// Summary statistics over repeated runs, all in nanoseconds.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    /// The raw samples, in the order they were taken.
+    pub samples: Vec<u64>,
+    pub min: u64,
+    pub max: u64,
+    pub median: f64,
+    pub mean: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub std_dev: f64,
+    /// Indices into `samples` which fall outside the tukey fences.
+    pub outliers: Vec<usize>,
+}
+impl Summary {
+    pub fn from_samples(samples: Vec<u64>) -> Self {
+        assert!(!samples.is_empty(), "summary of zero samples");
+
+        let mut sorted = samples.clone();
+        sorted.sort();
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / n;
+        let variance = if samples.len() > 1 {
+            samples.iter()
+                .map(|&s| (s as f64 - mean) * (s as f64 - mean))
+                .sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+
+        // tukey's fences: anything more than 1.5 interquartile ranges outside of the
+        // quartiles is flagged
+        let q1 = percentile(&sorted, 25.0);
+        let q3 = percentile(&sorted, 75.0);
+        let iqr = q3 - q1;
+        let (low_fence, high_fence) = (q1 - 1.5 * iqr, q3 + 1.5 * iqr);
+        let outliers = samples.iter()
+            .enumerate()
+            .filter(|&(_, &s)| (s as f64) < low_fence || (s as f64) > high_fence)
+            .map(|(i, _)| i)
+            .collect();
+
+        Summary {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            median: percentile(&sorted, 50.0),
+            mean,
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+            std_dev: variance.sqrt(),
+            outliers,
+            samples,
+        }
+    }
+}
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "    min      {:>16} ns", self.min)?;
+        writeln!(f, "    median   {:>16.0} ns", self.median)?;
+        writeln!(f, "    mean     {:>16.0} ns", self.mean)?;
+        writeln!(f, "    p95      {:>16.0} ns", self.p95)?;
+        writeln!(f, "    p99      {:>16.0} ns", self.p99)?;
+        writeln!(f, "    max      {:>16} ns", self.max)?;
+        writeln!(f, "    std dev  {:>16.0} ns", self.std_dev)?;
+        if self.outliers.is_empty() {
+            write!(f, "    outliers none")
+        } else {
+            let reps: Vec<String> = self.outliers.iter()
+                .map(|&i| format!("{} ({} ns)", i, self.samples[i]))
+                .collect();
+            write!(f, "    outliers {} of {}: rep {}",
+                   self.outliers.len(), self.samples.len(), reps.join(", "))
+        }
+    }
+}
+
+/// The p-th percentile of an already-sorted sample, linearly interpolating between
+/// the closest ranks.
+pub fn percentile(sorted: &[u64], p: f64) -> f64 {
+    match sorted.len() {
+        0 => panic!("percentile of zero samples"),
+        1 => sorted[0] as f64,
+        len => {
+            let rank = (p / 100.0) * (len - 1) as f64;
+            let below = rank.floor() as usize;
+            let above = rank.ceil() as usize;
+            let frac = rank - below as f64;
+            sorted[below] as f64 + (sorted[above] as f64 - sorted[below] as f64) * frac
+        }
+    }
+}
+
+/// Run the ops against a fresh tree `config.warmup` times without recording, then
+/// `config.repetitions` times, recording each run.
+pub fn run<T: Ord + Debug + Clone, B: Bst<T>>(ops: &[Op<T>], config: &RunConfig) -> Summary
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    for _ in 0..config.warmup {
+        time_ns::<T, B>(ops.to_vec());
+    }
+    let samples = (0..config.repetitions)
+        .map(|_| time_ns::<T, B>(ops.to_vec()))
+        .collect();
+    Summary::from_samples(samples)
+}
+
+/// The two-sided p-value of a mann-whitney u test of whether two samples come from the
+/// same distribution, using the normal approximation with a correction for ties.
+///
+/// Unlike a t-test this doesn't assume the timings are normally distributed, which they
+/// usually aren't, being bounded below and having a long tail.
+#[allow(clippy::many_single_char_names)]
+pub fn mann_whitney_p(a: &[u64], b: &[u64]) -> f64 {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return 1.0;
+    }
+
+    // rank the pooled samples, giving tied samples the average of their ranks
+    let mut pooled: Vec<(u64, bool)> = a.iter().map(|&s| (s, true))
+        .chain(b.iter().map(|&s| (s, false)))
+        .collect();
+    pooled.sort();
+    let mut rank_sum_a = 0.0;
+    let mut tie_correction = 0.0;
+    let mut i = 0;
+    while i < pooled.len() {
+        let mut j = i;
+        while j < pooled.len() && pooled[j].0 == pooled[i].0 {
+            j += 1;
+        }
+        // ranks are 1-based, so this group holds ranks i + 1 ..= j
+        let avg_rank = (i + 1 + j) as f64 / 2.0;
+        let in_a = pooled[i..j].iter().filter(|&&(_, from_a)| from_a).count();
+        rank_sum_a += avg_rank * in_a as f64;
+        let ties = (j - i) as f64;
+        tie_correction += ties * ties * ties - ties;
+        i = j;
+    }
+
+    let n = n1 + n2;
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_correction / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    // with a continuity correction
+    let z = ((u - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+    (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+}
+
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1.0 + erf(z / 2f64.sqrt()))
+}
+
+// abramowitz and stegun 7.1.26, accurate to about 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741
+        + t * (-1.453_152_027 + t * 1.061_405_429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn percentiles_interpolate() {
+    let sorted = [15, 20, 35, 40, 50];
+    assert_eq!(percentile(&sorted, 0.0), 15.0);
+    assert_eq!(percentile(&sorted, 25.0), 20.0);
+    assert_eq!(percentile(&sorted, 40.0), 29.0);
+    assert_eq!(percentile(&sorted, 50.0), 35.0);
+    assert_eq!(percentile(&sorted, 95.0), 48.0);
+    assert_eq!(percentile(&sorted, 100.0), 50.0);
+    assert_eq!(percentile(&[7], 99.0), 7.0);
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn summary_flags_outliers() {
+    // the quartiles are 2 and 4, so the fences are at -1 and 7
+    let summary = Summary::from_samples(vec![3, 1, 2, 4, 100]);
+    assert_eq!((summary.min, summary.max), (1, 100));
+    assert_eq!(summary.median, 3.0);
+    assert_eq!(summary.mean, 22.0);
+    assert!((summary.std_dev - 43.617_657).abs() < 1e-6);
+    assert_eq!(summary.outliers, vec![4]);
+
+    assert!(Summary::from_samples(vec![10, 11, 12, 13, 14]).outliers.is_empty());
+    assert_eq!(Summary::from_samples(vec![5]).std_dev, 0.0);
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn mann_whitney_matches_reference() {
+    // reference values from the normal approximation with tie and continuity corrections,
+    // as scipy.stats.mannwhitneyu(a, b, method="asymptotic") computes them
+    let close = |a: &[u64], b: &[u64], p: f64| (mann_whitney_p(a, b) - p).abs() < 1e-6;
+
+    let low: Vec<u64> = (1..11).collect();
+    let high: Vec<u64> = (11..21).collect();
+    assert!(close(&low, &high, 0.000_182_672));
+    assert!(close(&high, &low, 0.000_182_672));
+    assert!(close(&[100, 102, 101, 105, 103, 104, 99, 100],
+                  &[110, 108, 111, 109, 112, 110, 107, 113], 0.000_922_886));
+    assert!(close(&[1, 2, 2, 3, 4, 5], &[2, 3, 3, 4, 6, 7], 0.254_719));
+
+    assert!(close(&low, &low, 1.0));
+    assert_eq!(mann_whitney_p(&[5, 5, 5], &[5, 5]), 1.0);
+    assert_eq!(mann_whitney_p(&[], &low), 1.0);
+}
@@ -0,0 +1,178 @@
+use super::output::BenchResult;
+
+use std::io::{self, Write};
+
+/// A geometric series of sizes from `min` up to and including `max`.
+pub fn sizes(min: usize, max: usize, factor: f64) -> Vec<usize> {
+    assert!(factor > 1.0, "sweep factor must be greater than 1");
+    let mut sizes = Vec::new();
+    let mut size = min.max(1) as f64;
+    while size.round() as usize <= max {
+        let rounded = size.round() as usize;
+        if sizes.last() != Some(&rounded) {
+            sizes.push(rounded);
+        }
+        size *= factor;
+    }
+    if sizes.last() != Some(&max) && max >= min {
+        sizes.push(max);
+    }
+    sizes
+}
+
+/// Median nanoseconds per op.
+pub fn ns_per_op(result: &BenchResult) -> f64 {
+    result.summary.median / result.counts.total().max(1) as f64
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct LinearFit {
+    pub slope: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+}
+
+/// Ordinary least squares fit of `y = slope * x + intercept`.
+pub fn linear_fit(points: &[(f64, f64)]) -> Option<LinearFit> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let sxx = points.iter().map(|&(x, _)| (x - mean_x) * (x - mean_x)).sum::<f64>();
+    let sxy = points.iter().map(|&(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+    let syy = points.iter().map(|&(_, y)| (y - mean_y) * (y - mean_y)).sum::<f64>();
+    if sxx == 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    Some(LinearFit {
+        slope,
+        intercept: mean_y - slope * mean_x,
+        r_squared: if syy == 0.0 { 1.0 } else { (sxy * sxy) / (sxx * syy) },
+    })
+}
+
+/// How ns/op grows with size for one backend.
+///
+/// A balanced tree should fit `a + b log2(n)` well, with a power law exponent near zero.
+/// An unbalanced tree degrading towards linear depth, or a working set falling out of
+/// cache, shows up as a rising local exponent between neighbouring sizes.
+pub struct Growth {
+    /// `ns/op = slope * log2(n) + intercept`
+    pub logarithmic: Option<LinearFit>,
+    /// `log(ns/op) = slope * log(n) + intercept`, so the slope is the exponent k in n^k.
+    pub power: Option<LinearFit>,
+    /// The exponent between each pair of neighbouring sizes, keyed by the larger size.
+    pub local_exponents: Vec<(usize, f64)>,
+}
+impl Growth {
+    pub fn of(points: &[(usize, f64)]) -> Self {
+        let log_points: Vec<(f64, f64)> = points.iter()
+            .map(|&(n, y)| ((n as f64).log2(), y))
+            .collect();
+        let power_points: Vec<(f64, f64)> = points.iter()
+            .filter(|&&(_, y)| y > 0.0)
+            .map(|&(n, y)| ((n as f64).ln(), y.ln()))
+            .collect();
+        let local_exponents = points.windows(2)
+            .filter(|pair| pair[0].1 > 0.0 && pair[1].1 > 0.0 && pair[1].0 > pair[0].0)
+            .map(|pair| {
+                let ((n0, y0), (n1, y1)) = (pair[0], pair[1]);
+                (n1, (y1 / y0).ln() / (n1 as f64 / n0 as f64).ln())
+            })
+            .collect();
+        Growth {
+            logarithmic: linear_fit(&log_points),
+            power: linear_fit(&power_points),
+            local_exponents,
+        }
+    }
+}
+
+pub fn write_table<W: Write>(out: &mut W, backends: &[&str], results: &[BenchResult])
+    -> io::Result<()> {
+
+    let mut sizes: Vec<usize> = results.iter().map(|r| r.workload.size).collect();
+    sizes.sort();
+    sizes.dedup();
+
+    write!(out, "{:>12}", "size")?;
+    for backend in backends {
+        write!(out, " {:>12} {:>8}", format!("{} ns/op", backend), "local k")?;
+    }
+    writeln!(out)?;
+
+    let growths: Vec<Growth> = backends.iter()
+        .map(|&backend| {
+            let points: Vec<(usize, f64)> = results.iter()
+                .filter(|r| r.backend == backend)
+                .map(|r| (r.workload.size, ns_per_op(r)))
+                .collect();
+            Growth::of(&points)
+        })
+        .collect();
+
+    for &size in &sizes {
+        write!(out, "{:>12}", size)?;
+        for (&backend, growth) in backends.iter().zip(&growths) {
+            let result = results.iter()
+                .find(|r| r.backend == backend && r.workload.size == size);
+            let local = growth.local_exponents.iter()
+                .find(|&&(n, _)| n == size)
+                .map(|&(_, k)| format!("{:+.2}", k))
+                .unwrap_or_default();
+            match result {
+                Some(result) => write!(out, " {:>12.1} {:>8}", ns_per_op(result), local)?,
+                None => write!(out, " {:>12} {:>8}", "-", "")?,
+            };
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out)?;
+    for (&backend, growth) in backends.iter().zip(&growths) {
+        write!(out, "{}:", backend)?;
+        if let Some(fit) = growth.logarithmic {
+            write!(out, " ns/op ~ {:.1} + {:.2} log2(n) (r^2 {:.3});",
+                   fit.intercept, fit.slope, fit.r_squared)?;
+        }
+        if let Some(fit) = growth.power {
+            write!(out, " ns/op ~ n^{:.3} (r^2 {:.3})", fit.slope, fit.r_squared)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn sizes_are_geometric() {
+    assert_eq!(sizes(1, 100, 2.0), [1, 2, 4, 8, 16, 32, 64, 100]);
+    assert_eq!(sizes(1000, 1000, 2.0), [1000]);
+    assert!(sizes(5, 3, 2.0).is_empty());
+    // sizes which round to the same integer are only run once
+    let small = sizes(1, 10, 1.1);
+    assert!(small.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", small);
+    assert_eq!((small[0], small[small.len() - 1]), (1, 10));
+}
+
+#[test]
+fn linear_fit_finds_lines() {
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+    let fit = linear_fit(&[(0.0, 1.0), (1.0, 3.0), (2.0, 5.0)]).unwrap();
+    assert!(close(fit.slope, 2.0) && close(fit.intercept, 1.0) && close(fit.r_squared, 1.0));
+    let fit = linear_fit(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0)]).unwrap();
+    assert!(close(fit.slope, 0.2) && close(fit.intercept, 0.2) && close(fit.r_squared, 0.2));
+    // a flat line is fit exactly
+    assert!(close(linear_fit(&[(1.0, 4.0), (2.0, 4.0)]).unwrap().r_squared, 1.0));
+
+    assert!(linear_fit(&[(1.0, 1.0)]).is_none());
+    assert!(linear_fit(&[(1.0, 1.0), (1.0, 2.0)]).is_none());
+
+    // ns/op growing linearly with n has exponent 1 between any two sizes
+    let growth = Growth::of(&[(10, 1.0), (100, 10.0), (1000, 100.0)]);
+    assert!(close(growth.power.unwrap().slope, 1.0));
+    assert!(growth.local_exponents.iter().all(|&(_, k)| close(k, 1.0)));
+}
@@ -0,0 +1,158 @@
+//! Benchmarking a tree shared between threads, each applying its own stream of ops.
+
+use bst::Bst;
+use bst::concurrent::{ConcurrentBst, ShardedBst};
+use bst::coupling::LockCouplingBst;
+use super::{Op, OpCounts, rand_ops};
+use super::stats::{RunConfig, Summary};
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use stopwatch::Stopwatch;
+
+/// How the threads share the tree.
+#[derive(Debug, Copy, Clone)]
+pub enum Sharing {
+    /// One tree behind one lock.
+    Locked,
+    /// This many trees, each behind its own lock.
+    Sharded(usize),
+}
+
+/// A `rand_ops` stream for each thread, from consecutive seeds.
+pub fn streams(num_threads: usize, num_ops: usize, seed: u64) -> Vec<Vec<Op<i32>>> {
+    (0..num_threads)
+        .map(|i| rand_ops(num_ops, seed.wrapping_add(i as u64)))
+        .collect()
+}
+
+/// The op counts over every stream together.
+pub fn counts(streams: &[Vec<Op<i32>>]) -> OpCounts {
+    let mut total = OpCounts::of::<i32>(&[]);
+    for stream in streams {
+        let counts = OpCounts::of(stream);
+        total.inserts += counts.inserts;
+        total.removes += counts.removes;
+        total.contains += counts.contains;
+    }
+    total
+}
+
+// a tree which threads share through `&self`. backends only forward the three ops, and
+// `apply` dispatches to them
+trait Shared: Send + Sync + 'static {
+    fn insert(&self, elem: i32) -> bool;
+
+    fn remove(&self, elem: &i32) -> bool;
+
+    fn contains(&self, elem: &i32) -> bool;
+
+    fn apply(&self, op: Op<i32>) {
+        match op {
+            Op::Insert(t) => { self.insert(t); },
+            Op::Remove(t) => { self.remove(&t); },
+            Op::Contains(t) => { self.contains(&t); },
+        };
+    }
+}
+impl<B: Bst<i32> + Send + Sync + 'static> Shared for ConcurrentBst<i32, B>
+    where for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    fn insert(&self, elem: i32) -> bool {
+        ConcurrentBst::insert(self, elem)
+    }
+
+    fn remove(&self, elem: &i32) -> bool {
+        ConcurrentBst::remove(self, elem)
+    }
+
+    fn contains(&self, elem: &i32) -> bool {
+        ConcurrentBst::contains(self, elem)
+    }
+}
+impl<B: Bst<i32> + Send + Sync + 'static> Shared for ShardedBst<i32, B>
+    where for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    fn insert(&self, elem: i32) -> bool {
+        ShardedBst::insert(self, elem)
+    }
+
+    fn remove(&self, elem: &i32) -> bool {
+        ShardedBst::remove(self, elem)
+    }
+
+    fn contains(&self, elem: &i32) -> bool {
+        ShardedBst::contains(self, elem)
+    }
+}
+impl Shared for LockCouplingBst<i32> {
+    fn insert(&self, elem: i32) -> bool {
+        LockCouplingBst::insert(self, elem)
+    }
+
+    fn remove(&self, elem: &i32) -> bool {
+        LockCouplingBst::remove(self, elem)
+    }
+
+    fn contains(&self, elem: &i32) -> bool {
+        LockCouplingBst::contains(self, elem)
+    }
+}
+
+/// The wall time from every thread starting together to the last one finishing.
+fn time_ns_shared<S: Shared>(tree: S, streams: &[Vec<Op<i32>>]) -> u64 {
+    let tree = Arc::new(tree);
+    // the ops are copied before the timer starts, and every thread waits on the barrier,
+    // so that spawning doesn't count
+    let barrier = Arc::new(Barrier::new(streams.len() + 1));
+    let threads: Vec<_> = streams.iter().cloned().map(|ops| {
+        let (tree, barrier) = (tree.clone(), barrier.clone());
+        thread::spawn(move || {
+            barrier.wait();
+            for op in ops {
+                tree.apply(op);
+            }
+        })
+    }).collect();
+    barrier.wait();
+    let timer = Stopwatch::start_new();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    timer.elapsed().as_nanos() as u64
+}
+
+pub fn time_ns<B: Bst<i32> + Send + Sync + 'static>(streams: &[Vec<Op<i32>>], sharing: Sharing) -> u64
+    where for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    match sharing {
+        Sharing::Locked => time_ns_shared(ConcurrentBst::<i32, B>::new(), streams),
+        Sharing::Sharded(shards) => time_ns_shared(ShardedBst::<i32, B>::new(shards), streams),
+    }
+}
+
+pub fn run<B: Bst<i32> + Send + Sync + 'static>(streams: &[Vec<Op<i32>>], sharing: Sharing,
+                                               config: &RunConfig) -> Summary
+    where for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    for _ in 0..config.warmup {
+        time_ns::<B>(streams, sharing);
+    }
+    let samples = (0..config.repetitions)
+        .map(|_| time_ns::<B>(streams, sharing))
+        .collect();
+    Summary::from_samples(samples)
+}
+
+/// As `run`, but for the lock coupling tree, which has its own locking rather than wrapping
+/// a `Bst`.
+pub fn run_lock_coupling(streams: &[Vec<Op<i32>>], config: &RunConfig) -> Summary {
+    for _ in 0..config.warmup {
+        time_ns_shared(LockCouplingBst::new(), streams);
+    }
+    let samples = (0..config.repetitions)
+        .map(|_| time_ns_shared(LockCouplingBst::new(), streams))
+        .collect();
+    Summary::from_samples(samples)
+}
@@ -1,58 +1,203 @@
 
 use super::Bst;
+use super::shape::{self, NodeRef, TreeStats};
+use super::validate::{self, InvariantError};
+use super::dot::{self, Annotation};
+use super::pretty::{self, PrettyConfig};
+use super::build::{self, NotSorted};
+use super::compare::{Compare, Natural};
+use super::snapshot::Snapshot;
+#[cfg(feature = "serde")]
+use super::serialize::{self, Shape};
+#[cfg(feature = "rayon")]
+use super::parallel;
 
 use std::cmp::Ordering;
 use std::mem;
-use std::iter::{IntoIterator, Iterator};
+use std::ptr;
+use std::iter::{FromIterator, IntoIterator, Iterator};
 use std::fmt::Debug;
 
 use bonzai::*;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "rayon")]
+use rayon::iter::plumbing::UnindexedConsumer;
 
+/// A tree in a bonzai arena, ordered by a comparator.
 #[derive(Debug)]
-pub struct BonzaiBst<T: Ord + Debug> {
+pub struct BonzaiBstBy<T: Debug, C: Compare<T>> {
     tree: Tree<T, [ChildId; 2]>,
+    len: usize,
+    cmp: C,
 }
-impl<T: Ord + Debug> Bst<T> for BonzaiBst<T> {
-    fn new() -> Self {
-        BonzaiBst {
+/// A tree in a bonzai arena, ordered by `Ord`.
+pub type BonzaiBst<T> = BonzaiBstBy<T, Natural>;
+impl<T: Debug, C: Compare<T>> BonzaiBstBy<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        BonzaiBstBy {
             tree: Tree::new(),
+            len: 0,
+            cmp,
         }
     }
 
-    fn insert(&mut self, elem: T) -> bool {
-        let mut op = self.tree.operation();
-        match op.write_root() {
-            Some(root) => insert_node(root, elem),
-            None => {
-                op.put_root_elem(elem);
-                true
-            },
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    pub fn insert(&mut self, elem: T) -> bool {
+        let inserted = {
+            let mut op = self.tree.operation();
+            match op.write_root() {
+                Some(root) => insert_node(root, elem, &self.cmp),
+                None => {
+                    op.put_root_elem(elem);
+                    true
+                },
+            }
+        };
+        if inserted {
+            self.len += 1;
         }
+        inserted
     }
 
-    fn remove(&mut self, elem: &T) -> bool {
-        let op = self.tree.operation();
-        let removed = match op.take_root() {
-            Some(root) => {
-                let (new_root, removed) = remove_node(root, elem);
-                if let Some(new_root) = new_root {
-                    op.try_put_root_tree(new_root).unwrap();
-                }
-                removed
-            },
-            None => false
+    pub fn remove(&mut self, elem: &T) -> bool {
+        let removed = {
+            let op = self.tree.operation();
+            // the root guard borrows the operation, so it must be dropped before the block ends
+            #[allow(clippy::let_and_return)]
+            let removed = match op.take_root() {
+                Some(root) => {
+                    let (new_root, removed) = remove_node(root, elem, &self.cmp);
+                    if let Some(new_root) = new_root {
+                        op.try_put_root_tree(new_root).unwrap();
+                    }
+                    removed
+                },
+                None => false
+            };
+            removed
         };
+        if removed {
+            self.len -= 1;
+        }
         removed
     }
 
-    fn contains(&self, elem: &T) -> bool {
+    pub fn contains(&self, elem: &T) -> bool {
         match self.tree.read_root() {
-            Some(root) => node_contains(root, elem),
+            Some(root) => node_contains(root, elem, &self.cmp),
             None => false,
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// As `Bst::from_sorted`, in the comparator's ordering.
+    pub fn from_sorted_by(elems: Vec<T>, cmp: C) -> Result<Self, NotSorted> {
+        build::check_sorted_by(&elems, &cmp)?;
+        Ok(Self::build_sorted(elems, cmp))
+    }
+
+    /// As `Bst::stats`. This and the methods below need only the comparator, so a tree
+    /// ordered by a key of elements which aren't `Ord` still has them.
+    pub fn stats(&self) -> Option<TreeStats> {
+        Some(shape::stats(self.tree.traverse_read_root()))
+    }
+
+    pub fn to_dot(&self, annotation: Option<Annotation>) -> Option<String> {
+        Some(dot::to_dot(self.tree.traverse_read_root(), annotation))
+    }
+
+    pub fn pretty(&self, config: &PrettyConfig) -> Option<String> {
+        Some(pretty::pretty(self.tree.traverse_read_root(), config))
+    }
+
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        let root = self.tree.traverse_read_root();
+        if let Some(ref root) = root {
+            if root.above_me() != AboveMe::Root {
+                return Err(InvariantError::new(Vec::new(), format!(
+                    "root node is owned by {:?}", root.above_me())));
+            }
+        }
+        validate::check_by(root, self.len, &self.cmp)
+    }
+
+    pub fn snapshot(&self) -> Snapshot<Self> where Self: Clone {
+        Snapshot::new(self.clone())
+    }
+
+    // the elements must already be known to be strictly increasing
+    fn build_sorted(elems: Vec<T>, cmp: C) -> Self {
+        let mut tree = BonzaiBstBy::with_comparator(cmp);
+        tree.len = build_preorder(&mut tree.tree, BalancedPreorder::new(elems));
+        tree
+    }
 }
-impl<'s, T: Ord + Debug> IntoIterator for &'s BonzaiBst<T> {
+impl<T: Ord + Debug, C: Compare<T> + Default + Debug> Bst<T> for BonzaiBstBy<T, C> {
+    fn new() -> Self {
+        Self::with_comparator(C::default())
+    }
+
+    fn from_sorted(elems: Vec<T>) -> Result<Self, NotSorted> {
+        Self::from_sorted_by(elems, C::default())
+    }
+
+    fn insert(&mut self, elem: T) -> bool {
+        BonzaiBstBy::insert(self, elem)
+    }
+
+    fn remove(&mut self, elem: &T) -> bool {
+        BonzaiBstBy::remove(self, elem)
+    }
+
+    fn contains(&self, elem: &T) -> bool {
+        BonzaiBstBy::contains(self, elem)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn stats(&self) -> Option<TreeStats> {
+        BonzaiBstBy::stats(self)
+    }
+
+    fn to_dot(&self, annotation: Option<Annotation>) -> Option<String> {
+        BonzaiBstBy::to_dot(self, annotation)
+    }
+
+    fn pretty(&self, config: &PrettyConfig) -> Option<String> {
+        BonzaiBstBy::pretty(self, config)
+    }
+
+    fn validate(&self) -> Result<(), InvariantError> {
+        BonzaiBstBy::validate(self)
+    }
+}
+/// Copies the tree node for node, keeping its shape, without comparing any elements.
+///
+/// bonzai 0.2 has no way to copy a tree's arena wholesale: `Tree` isn't `Clone`, and its
+/// node storage is private, so nodes can only be added through an operation. The copy is
+/// laid out in batches of operations instead, which still beats inserting the elements,
+/// as `clone_beats_rebuilding` times.
+impl<T: Debug + Clone, C: Compare<T> + Clone> Clone for BonzaiBstBy<T, C> {
+    fn clone(&self) -> Self {
+        let mut copy = BonzaiBstBy::with_comparator(self.cmp.clone());
+        copy.len = build_preorder(&mut copy.tree, Preorder::new(&self.tree));
+        copy
+    }
+}
+impl<'s, T: Debug, C: Compare<T>> IntoIterator for &'s BonzaiBstBy<T, C> {
     type Item = &'s T;
     type IntoIter = Iter<'s, T>;
 
@@ -65,10 +210,244 @@ impl<'s, T: Ord + Debug> IntoIterator for &'s BonzaiBst<T> {
         }
     }
 }
+impl<T: Debug, C: Compare<T> + Default> FromIterator<T> for BonzaiBstBy<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let elems: Vec<T> = iter.into_iter().collect();
+        let cmp = C::default();
+        if build::check_sorted_by(&elems, &cmp).is_ok() {
+            return Self::build_sorted(elems, cmp);
+        }
+        let mut tree = Self::with_comparator(cmp);
+        for elem in elems {
+            tree.insert(elem);
+        }
+        tree
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Ord + Debug> Shape<T> for BonzaiBst<T> {
+    fn preorder(&self) -> Vec<Option<&T>> {
+        serialize::preorder(self.tree.traverse_read_root())
+    }
 
-fn insert_node<T: Ord>(node: NodeWriteGuard<T, [ChildId; 2]>, elem: T) -> bool {
+    fn from_checked_preorder(tokens: Vec<Option<T>>) -> Self {
+        let mut tree = BonzaiBst::new();
+        tree.len = build_preorder(&mut tree.tree, tokens.into_iter());
+        tree
+    }
+}
+
+impl<'t, T> NodeRef<'t, T> for TreeReadTraverser<'t, T, [ChildId; 2]> {
+    fn elem(&self) -> &'t T {
+        TreeReadTraverser::elem(self)
+    }
+
+    fn child(&self, branch: usize) -> Option<Self> {
+        TreeReadTraverser::child(self, branch).unwrap().ok()
+    }
+
+    fn check_link(&self, branch: usize, child: &Self) -> Result<(), String> {
+        // nodes are identified by the address of their element in the arena
+        match child.parent() {
+            Ok(ref parent) if ptr::eq(parent.elem(), self.elem()) => (),
+            Ok(_) => return Err(format!("child {} links back to a different parent", branch)),
+            Err(e) => return Err(format!("child {} has no parent link: {:?}", branch, e)),
+        };
+        match child.this_branch_index() {
+            Ok(index) if index == branch => Ok(()),
+            Ok(index) => Err(format!("child {} believes it is child {}", branch, index)),
+            Err(e) => Err(format!("child {} has no branch index: {:?}", branch, e)),
+        }
+    }
+}
+
+// the most nodes added in one operation by `build_preorder`
+const BUILD_BATCH: usize = 256;
+
+// build a tree, which must be empty, from a well formed pre-order sequence in which each
+// `None` marks an empty subtree, returning the number of nodes. a bonzai tree chains the
+// nodes added during one operation into a list of small chunks, which is walked on every
+// access until the operation ends, so the nodes are added a bounded batch per operation,
+// each one attached under its parent, found again by index in later operations.
+fn build_preorder<T, I: Iterator<Item = Option<T>>>(tree: &mut Tree<T, [ChildId; 2]>, tokens: I) -> usize {
+    let mut tokens = tokens.fuse();
+    let mut count = 0;
+    // the positions still to be filled, the next one on top, as the parent and branch,
+    // or no parent for the root
+    let mut positions: Vec<(Option<NodeIndex>, usize)> = vec![(None, 0)];
+    while !positions.is_empty() {
+        let mut op = tree.operation();
+        let mut added = 0;
+        while added < BUILD_BATCH {
+            let (parent, branch) = match positions.pop() {
+                Some(position) => position,
+                None => break,
+            };
+            let elem = match tokens.next() {
+                Some(Some(elem)) => elem,
+                Some(None) => continue,
+                None => {
+                    positions.clear();
+                    break;
+                },
+            };
+            let index = match parent {
+                Some(parent) => {
+                    let mut parent = op.traverse_from(parent).unwrap().into_write_guard();
+                    let node = parent.op.new_detached(elem);
+                    let index = (&node).into_read_guard().index();
+                    parent.children().put_child_tree(branch, node).unwrap();
+                    index
+                },
+                None => {
+                    let node = op.new_detached(elem);
+                    let index = (&node).into_read_guard().index();
+                    op.try_put_root_tree(node).unwrap();
+                    index
+                },
+            };
+            positions.push((Some(index), 1));
+            positions.push((Some(index), 0));
+            added += 1;
+        }
+        count += added;
+    }
+    count
+}
+
+// the pre-order sequence of the balanced tree of strictly increasing elements
+struct BalancedPreorder<T> {
+    elems: Vec<Option<T>>,
+    // the ranges of elements still to be visited, the next one on top
+    ranges: Vec<(usize, usize)>,
+}
+impl<T> BalancedPreorder<T> {
+    fn new(elems: Vec<T>) -> Self {
+        let len = elems.len();
+        BalancedPreorder {
+            elems: elems.into_iter().map(Some).collect(),
+            ranges: vec![(0, len)],
+        }
+    }
+}
+impl<T> Iterator for BalancedPreorder<T> {
+    type Item = Option<T>;
+
+    fn next(&mut self) -> Option<Option<T>> {
+        let (start, end) = self.ranges.pop()?;
+        if start == end {
+            return Some(None);
+        }
+        let mid = start + build::left_len(end - start);
+        self.ranges.push((mid + 1, end));
+        self.ranges.push((start, mid));
+        Some(self.elems[mid].take())
+    }
+}
+
+// the pre-order sequence of copies of a tree's elements
+struct Preorder<'t, T: 't> {
+    // the subtrees still to be visited, the next one on top
+    stack: Vec<Option<TreeReadTraverser<'t, T, [ChildId; 2]>>>,
+}
+impl<'t, T> Preorder<'t, T> {
+    fn new(tree: &'t Tree<T, [ChildId; 2]>) -> Self {
+        Preorder {
+            stack: vec![tree.traverse_read_root()],
+        }
+    }
+}
+impl<'t, T: Clone> Iterator for Preorder<'t, T> {
+    type Item = Option<T>;
+
+    fn next(&mut self) -> Option<Option<T>> {
+        let node = match self.stack.pop()? {
+            Some(node) => node,
+            None => return Some(None),
+        };
+        self.stack.push(node.child(1).unwrap().ok());
+        self.stack.push(node.child(0).unwrap().ok());
+        Some(Some(node.elem().clone()))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Ord + Debug + Send + Sync> BonzaiBst<T> {
+    /// As `from_sorted`, but checking the order and laying the elements out in pre-order in
+    /// parallel. The nodes are then linked on one thread, since every bonzai 0.2 operation
+    /// borrows the whole arena, so subtrees can't be built apart and attached afterwards.
+    pub fn par_from_sorted(elems: Vec<T>) -> Result<Self, NotSorted> {
+        parallel::check_sorted(&elems)?;
+        Ok(Self::par_build_sorted(elems))
+    }
+
+    fn par_build_sorted(elems: Vec<T>) -> Self {
+        let mut tree = BonzaiBst::new();
+        tree.len = build_preorder(&mut tree.tree, parallel::balanced_preorder(elems).into_iter());
+        tree
+    }
+
+    fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut elems = Vec::with_capacity(self.len);
+        {
+            let op = self.tree.operation();
+            // detach each node's left subtree before taking its element, and then its
+            // right subtree after
+            let mut stack = Vec::new();
+            stack.extend(op.take_root());
+            while let Some(mut node) = stack.pop() {
+                let left = node.children().take_child(0).unwrap();
+                match left {
+                    Some(left) => {
+                        stack.push(node);
+                        stack.push(left);
+                    },
+                    None => {
+                        let right = node.children().take_child(1).unwrap();
+                        elems.push(node.into_elem());
+                        stack.extend(right);
+                    },
+                }
+            }
+        }
+        elems
+    }
+}
+/// Collects the new elements, sorts and deduplicates them in parallel, and merges them with
+/// the tree's elements, then rebuilds the tree balanced, as `par_from_sorted` does.
+#[cfg(feature = "rayon")]
+impl<T: Ord + Debug + Send + Sync> ParallelExtend<T> for BonzaiBst<T> {
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, par_iter: I) {
+        let new = par_iter.into_par_iter().collect();
+        let existing = mem::replace(self, BonzaiBst::new()).into_sorted_vec();
+        *self = Self::par_build_sorted(parallel::merge_new(existing, new));
+    }
+}
+#[cfg(feature = "rayon")]
+impl<'s, T: Ord + Debug + Sync> IntoParallelIterator for &'s BonzaiBst<T> {
+    type Item = &'s T;
+    type Iter = ParIter<'s, T>;
+
+    fn into_par_iter(self) -> ParIter<'s, T> {
+        ParIter(parallel::ParIter::new(self.tree.traverse_read_root()))
+    }
+}
+
+#[cfg(feature = "rayon")]
+pub struct ParIter<'t, T: Ord + Debug + 't>(parallel::ParIter<'t, T, TreeReadTraverser<'t, T, [ChildId; 2]>>);
+#[cfg(feature = "rayon")]
+impl<'t, T: Ord + Debug + Sync + 't> ParallelIterator for ParIter<'t, T> {
+    type Item = &'t T;
+
+    fn drive_unindexed<C: UnindexedConsumer<&'t T>>(self, consumer: C) -> C::Result {
+        self.0.drive_unindexed(consumer)
+    }
+}
+
+fn insert_node<T, C: Compare<T>>(node: NodeWriteGuard<T, [ChildId; 2]>, elem: T, cmp: &C) -> bool {
     let (node_elem, mut children) = node.into_split();
-    let recurse_into: usize = match elem.cmp(node_elem) {
+    let recurse_into: usize = match cmp.compare(&elem, node_elem) {
         Ordering::Equal => {
             return false;
         },
@@ -76,7 +455,7 @@ fn insert_node<T: Ord>(node: NodeWriteGuard<T, [ChildId; 2]>, elem: T) -> bool {
         Ordering::Less => 0,
     };
     match children.borrow_child_write(recurse_into).unwrap() {
-        Some(child) => insert_node(child, elem),
+        Some(child) => insert_node(child, elem, cmp),
         None => {
             children.put_child_elem(recurse_into, elem).unwrap();
             true
@@ -84,9 +463,9 @@ fn insert_node<T: Ord>(node: NodeWriteGuard<T, [ChildId; 2]>, elem: T) -> bool {
     }
 }
 
-fn remove_node<'o, 't: 'o, T: Ord>(mut node: NodeOwnedGuard<'o, 't, T, [ChildId; 2]>, elem: &T)
-    -> (Option<NodeOwnedGuard<'o, 't, T, [ChildId; 2]>>, bool) {
-    let recurse_into: Option<usize> = match elem.cmp(&*node.elem()) {
+fn remove_node<'o, 't: 'o, T, C>(mut node: NodeOwnedGuard<'o, 't, T, [ChildId; 2]>, elem: &T, cmp: &C)
+    -> (Option<NodeOwnedGuard<'o, 't, T, [ChildId; 2]>>, bool) where C: Compare<T> {
+    let recurse_into: Option<usize> = match cmp.compare(elem, &*node.elem()) {
         Ordering::Equal => None,
         Ordering::Greater => Some(1),
         Ordering::Less => Some(0),
@@ -99,7 +478,7 @@ fn remove_node<'o, 't: 'o, T: Ord>(mut node: NodeOwnedGuard<'o, 't, T, [ChildId;
             match children.take_child(branch).unwrap() {
                 Some(child) => {
                     // move the detached child through a recursion of this function
-                    let (new_child, removed) = remove_node(child, elem);
+                    let (new_child, removed) = remove_node(child, elem, cmp);
                     if let Some(new_child) = new_child {
                         // if it produced a replacement child, reattach it
                         children.put_child_tree(branch, new_child).unwrap();
@@ -153,7 +532,7 @@ fn remove_node<'o, 't: 'o, T: Ord>(mut node: NodeOwnedGuard<'o, 't, T, [ChildId;
     }
 }
 
-fn detach_leftmost<'o, 't: 'o, T: Ord>(mut node: NodeOwnedGuard<'o, 't, T, [ChildId; 2]>)
+fn detach_leftmost<'o, 't: 'o, T>(mut node: NodeOwnedGuard<'o, 't, T, [ChildId; 2]>)
     -> (Option<NodeOwnedGuard<'o, 't, T, [ChildId; 2]>>, T) {
 
     let mut children = node.children();
@@ -165,7 +544,7 @@ fn detach_leftmost<'o, 't: 'o, T: Ord>(mut node: NodeOwnedGuard<'o, 't, T, [Chil
                 children.put_child_tree(0, new_child).unwrap();
             }
             mem::drop(children);
-            return (Some(node), elem);
+            (Some(node), elem)
         },
         None => {
             // if no left child exists, detach this elem, and become right child, if present
@@ -176,8 +555,8 @@ fn detach_leftmost<'o, 't: 'o, T: Ord>(mut node: NodeOwnedGuard<'o, 't, T, [Chil
     }
 }
 
-fn node_contains<T: Ord>(node: NodeReadGuard<T, [ChildId; 2]>, elem: &T) -> bool {
-    let recurse_into: usize = match elem.cmp(&*node) {
+fn node_contains<T, C: Compare<T>>(node: NodeReadGuard<T, [ChildId; 2]>, elem: &T, cmp: &C) -> bool {
+    let recurse_into: usize = match cmp.compare(elem, &*node) {
         Ordering::Equal => {
             return true;
         },
@@ -185,63 +564,144 @@ fn node_contains<T: Ord>(node: NodeReadGuard<T, [ChildId; 2]>, elem: &T) -> bool
         Ordering::Less => 0,
     };
     match node.child(recurse_into).unwrap() {
-        Some(child) => node_contains(child, elem),
+        Some(child) => node_contains(child, elem, cmp),
         None => false
     }
 }
 
-pub struct Iter<'t, T: Ord + Debug> {
+pub struct Iter<'t, T: Debug> {
     traverser: Option<TreeReadTraverser<'t, T, [ChildId; 2]>>
 }
-impl<'t, T: Ord + Debug> Iter<'t, T> {
-    fn new(mut trav: TreeReadTraverser<'t, T, [ChildId; 2]>) -> Self {
-        // initially seek the leftmost node
-        Self::seek_leftmost(&mut trav);
+impl<'t, T: Debug> Iter<'t, T> {
+    fn new(trav: TreeReadTraverser<'t, T, [ChildId; 2]>) -> Self {
+        // initially seek the leftmost node, which holds the least element
+        Self::seek_leftmost(&trav);
         Iter {
             traverser: Some(trav)
         }
     }
 
     fn seek_leftmost(trav: &TreeReadTraverser<'t, T, [ChildId; 2]>) {
-        while match trav.seek_child(0).unwrap() {
-            Ok(_) => true,
-            Err(_) => match trav.seek_child(1).unwrap() {
-                Ok(_) => true,
-                Err(_) => false
-            }
-        } {}
+        while trav.seek_child(0).unwrap().is_ok() {}
     }
 }
-impl<'t, T: Ord + Debug> Iterator for Iter<'t, T> {
+impl<'t, T: Debug> Iterator for Iter<'t, T> {
     type Item = &'t T;
 
     fn next(&mut self) -> Option<&'t T> {
-        match self.traverser {
-            Some(ref mut trav) => {
+        let done = match self.traverser {
+            Some(ref trav) => {
                 let curr = trav.elem();
 
-                // move up, and if we moved up from the left child, seek the leftmost node
-                // of the right subtree
-                match trav.this_branch_index() {
-                    Ok(this_branch_index) => {
-                        trav.seek_parent().unwrap();
-                        if this_branch_index == 0 {
-                            if trav.seek_child(1).unwrap().is_ok() {
-                                // only seek the leftmost child if we actually have a right child
-                                Self::seek_leftmost(trav);
+                // the next element is the leftmost node of the right subtree, if there is one
+                if trav.seek_child(1).unwrap().is_ok() {
+                    Self::seek_leftmost(trav);
+                    return Some(curr);
+                }
+                // otherwise it's the first ancestor which we reach by moving up from its
+                // left subtree
+                loop {
+                    match trav.this_branch_index() {
+                        Ok(this_branch_index) => {
+                            trav.seek_parent().unwrap();
+                            if this_branch_index == 0 {
+                                return Some(curr);
                             }
-                        }
-                    },
-                    Err(_) => {
+                        },
                         // however, if we've hit the top, that means that we're done iterating
-                        mem::drop(trav);
-                        self.traverser = None;
-                    }
+                        Err(_) => break curr,
+                    };
                 }
-
-                Some(curr)
             },
-            None => None
+            None => return None,
+        };
+        self.traverser = None;
+        Some(done)
+    }
+}
+
+#[test]
+fn clone_copies_degenerate_chain() {
+    // a chain leaning right, as ascending inserts would make, built directly since those
+    // inserts would take quadratic time
+    let len = 100_000;
+    let mut tree: BonzaiBst<i32> = BonzaiBst::new();
+    let tokens = (0..len).flat_map(|i| vec![Some(i), None]).chain(Some(None));
+    tree.len = build_preorder(&mut tree.tree, tokens);
+    assert_eq!(tree.stats().unwrap().height as i32, len);
+
+    let copy = tree.clone();
+    assert_eq!(copy.stats(), tree.stats());
+    assert!(copy.validate().is_ok());
+    assert!((&copy).into_iter().eq(&tree));
+}
+
+#[test]
+fn validate_walks_degenerate_chain() {
+    // a chain leaning left, as descending inserts would make, which validation once walked
+    // copying the whole path to every node
+    let len = 100_000;
+    let mut tree: BonzaiBst<i32> = BonzaiBst::new();
+    let tokens = (0..len).rev().map(Some).chain((0..len + 1).map(|_| None));
+    tree.len = build_preorder(&mut tree.tree, tokens);
+    assert_eq!(tree.stats().unwrap().height as i32, len);
+    assert!(tree.validate().is_ok());
+
+    tree.len += 1;
+    assert!(tree.validate().unwrap_err().path.is_empty());
+}
+
+#[test]
+fn validate_finds_relinked_subtree() {
+    // 2 at the root, with 1 and 3 below it. moving 3 to the left of 1 through the operation
+    // leaves either its links or its order wrong there, depending on what bonzai relinks,
+    // and either must be reported at 1
+    let mut tree = BonzaiBst::from_sorted(vec![1, 2, 3]).unwrap();
+    {
+        let op = tree.tree.operation();
+        let mut root = op.take_root().unwrap();
+        {
+            let mut children = root.children();
+            let right = children.take_child(1).unwrap().unwrap();
+            let mut left = children.take_child(0).unwrap().unwrap();
+            {
+                let mut left_children = left.children();
+                left_children.put_child_tree(0, right).unwrap();
+            }
+            children.put_child_tree(0, left).unwrap();
         }
+        op.try_put_root_tree(root).unwrap();
     }
-}
\ No newline at end of file
+    assert_eq!(tree.validate().unwrap_err().path, [0]);
+}
+
+// a timing, so run it in release with --ignored
+#[test]
+#[ignore]
+fn clone_beats_rebuilding() {
+    use benchmark::seeded_rng;
+    use rand::Rng;
+    use stopwatch::Stopwatch;
+
+    let mut rng = seeded_rng(0);
+    let mut tree: BonzaiBst<i32> = BonzaiBst::new();
+    for _ in 0..200_000 {
+        tree.insert(rng.gen());
+    }
+
+    let timer = Stopwatch::start_new();
+    let copy = tree.clone();
+    let clone_ns = timer.elapsed().as_nanos();
+
+    // the alternative without arena access: inserting the elements in pre-order, which
+    // gives the same shape, but compares all the way down for every node
+    let timer = Stopwatch::start_new();
+    let mut rebuilt: BonzaiBst<i32> = BonzaiBst::new();
+    for elem in Preorder::new(&tree.tree).filter_map(|elem| elem) {
+        rebuilt.insert(elem);
+    }
+    let rebuild_ns = timer.elapsed().as_nanos();
+
+    assert_eq!(copy.stats(), rebuilt.stats());
+    assert!(clone_ns < rebuild_ns, "clone took {}ns, rebuilding {}ns", clone_ns, rebuild_ns);
+}
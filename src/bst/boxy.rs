@@ -1,51 +1,213 @@
 
 use super::Bst;
+use super::shape::{self, NodeRef, TreeStats};
+use super::validate::{self, InvariantError};
+use super::dot::{self, Annotation};
+use super::pretty::{self, PrettyConfig};
+use super::build::{self, NotSorted};
+use super::compare::{Compare, Natural};
+use super::snapshot::Snapshot;
+#[cfg(feature = "serde")]
+use super::serialize::{self, Shape};
+#[cfg(feature = "rayon")]
+use super::parallel;
 
 use std::cmp::Ordering;
-use std::iter::{IntoIterator, Iterator};
+use std::iter::{FromIterator, IntoIterator, Iterator};
 use std::fmt::Debug;
 
+#[cfg(feature = "rayon")]
+use rayon::{self, prelude::*};
+#[cfg(feature = "rayon")]
+use rayon::iter::plumbing::UnindexedConsumer;
+
+/// A tree of boxed nodes, ordered by a comparator.
 #[derive(Debug)]
-pub struct BoxBst<T: Ord + Debug> {
-    root: Option<BoxBstNode<T>>
+pub struct BoxBstBy<T: Debug, C: Compare<T>> {
+    root: Option<BoxBstNode<T>>,
+    len: usize,
+    cmp: C,
 }
-impl<T: Ord + Debug> Bst<T> for BoxBst<T> {
-    fn new() -> Self {
-        BoxBst {
-            root: None
+/// A tree of boxed nodes, ordered by `Ord`.
+pub type BoxBst<T> = BoxBstBy<T, Natural>;
+impl<T: Debug, C: Compare<T>> BoxBstBy<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        BoxBstBy {
+            root: None,
+            len: 0,
+            cmp,
         }
     }
 
-    fn insert(&mut self, elem: T) -> bool {
-        match self.root {
-            Some(ref mut root) => root.insert(elem),
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    pub fn insert(&mut self, elem: T) -> bool {
+        let inserted = match self.root {
+            Some(ref mut root) => root.insert(elem, &self.cmp),
             None => {
                 self.root = Some(BoxBstNode::new(elem));
                 true
             }
+        };
+        if inserted {
+            self.len += 1;
         }
+        inserted
     }
 
-    fn remove(&mut self, elem: &T) -> bool {
+    pub fn remove(&mut self, elem: &T) -> bool {
         if let Some(root) = self.root.take() {
-            let (new_root, removed) = root.remove(elem);
+            let (new_root, removed) = root.remove(elem, &self.cmp);
             if let Some(new_root) = new_root {
                 self.root = Some(new_root);
             }
+            if removed {
+                self.len -= 1;
+            }
             removed
         } else {
             false
         }
     }
 
-    fn contains(&self, elem: &T) -> bool {
+    pub fn contains(&self, elem: &T) -> bool {
         match self.root {
-            Some(ref root) => root.contains(elem),
+            Some(ref root) => root.contains(elem, &self.cmp),
             None => false
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// As `Bst::from_sorted`, in the comparator's ordering.
+    pub fn from_sorted_by(elems: Vec<T>, cmp: C) -> Result<Self, NotSorted> {
+        build::check_sorted_by(&elems, &cmp)?;
+        Ok(Self::build_sorted(elems, cmp))
+    }
+
+    /// As `Bst::stats`. This and the methods below need only the comparator, so a tree
+    /// ordered by a key of elements which aren't `Ord` still has them.
+    pub fn stats(&self) -> Option<TreeStats> {
+        Some(shape::stats(self.root.as_ref()))
+    }
+
+    pub fn to_dot(&self, annotation: Option<Annotation>) -> Option<String> {
+        Some(dot::to_dot(self.root.as_ref(), annotation))
+    }
+
+    pub fn pretty(&self, config: &PrettyConfig) -> Option<String> {
+        Some(pretty::pretty(self.root.as_ref(), config))
+    }
+
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        validate::check_by(self.root.as_ref(), self.len, &self.cmp)
+    }
+
+    pub fn snapshot(&self) -> Snapshot<Self> where Self: Clone {
+        Snapshot::new(self.clone())
+    }
+
+    // the elements must already be known to be strictly increasing
+    fn build_sorted(elems: Vec<T>, cmp: C) -> Self {
+        let len = elems.len();
+        BoxBstBy {
+            root: BoxBstNode::balanced(len, &mut elems.into_iter()),
+            len,
+            cmp,
+        }
+    }
+}
+impl<T: Ord + Debug, C: Compare<T> + Default + Debug> Bst<T> for BoxBstBy<T, C> {
+    fn new() -> Self {
+        Self::with_comparator(C::default())
+    }
+
+    fn from_sorted(elems: Vec<T>) -> Result<Self, NotSorted> {
+        Self::from_sorted_by(elems, C::default())
+    }
+
+    fn insert(&mut self, elem: T) -> bool {
+        BoxBstBy::insert(self, elem)
+    }
+
+    fn remove(&mut self, elem: &T) -> bool {
+        BoxBstBy::remove(self, elem)
+    }
+
+    fn contains(&self, elem: &T) -> bool {
+        BoxBstBy::contains(self, elem)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn stats(&self) -> Option<TreeStats> {
+        BoxBstBy::stats(self)
+    }
+
+    fn to_dot(&self, annotation: Option<Annotation>) -> Option<String> {
+        BoxBstBy::to_dot(self, annotation)
+    }
+
+    fn pretty(&self, config: &PrettyConfig) -> Option<String> {
+        BoxBstBy::pretty(self, config)
+    }
+
+    fn validate(&self) -> Result<(), InvariantError> {
+        BoxBstBy::validate(self)
+    }
+}
+/// A deep copy, made without recursion, so that degenerate trees can't overflow the stack.
+impl<T: Debug + Clone, C: Compare<T> + Clone> Clone for BoxBstBy<T, C> {
+    fn clone(&self) -> Self {
+        // visit nodes in post order, so each node's copied subtrees are on top of the copies
+        // stack, right above left, by the time the node itself is copied
+        let mut stack: Vec<(&BoxBstNode<T>, bool)> = Vec::new();
+        let mut copies: Vec<Box<BoxBstNode<T>>> = Vec::new();
+        stack.extend(self.root.as_ref().map(|root| (root, false)));
+        while let Some((node, expanded)) = stack.pop() {
+            if !expanded {
+                stack.push((node, true));
+                stack.extend(node.children[1].as_ref().map(|right| (&**right, false)));
+                stack.extend(node.children[0].as_ref().map(|left| (&**left, false)));
+                continue;
+            }
+            let right = node.children[1].as_ref().map(|_| copies.pop().unwrap());
+            let left = node.children[0].as_ref().map(|_| copies.pop().unwrap());
+            copies.push(Box::new(BoxBstNode {
+                elem: node.elem.clone(),
+                children: [left, right],
+            }));
+        }
+        BoxBstBy {
+            root: copies.pop().map(|root| *root),
+            len: self.len,
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+/// Frees the nodes without recursion, so that degenerate trees can't overflow the stack.
+impl<T: Debug, C: Compare<T>> Drop for BoxBstBy<T, C> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Box<BoxBstNode<T>>> = Vec::new();
+        if let Some(mut root) = self.root.take() {
+            stack.extend(root.children.iter_mut().filter_map(Option::take));
+        }
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.children.iter_mut().filter_map(Option::take));
+        }
+    }
 }
-impl<'s, T: Ord + Debug> IntoIterator for &'s BoxBst<T> {
+impl<'s, T: Debug, C: Compare<T>> IntoIterator for &'s BoxBstBy<T, C> {
     type Item = &'s T;
     type IntoIter = Iter<'s, T>;
 
@@ -53,18 +215,108 @@ impl<'s, T: Ord + Debug> IntoIterator for &'s BoxBst<T> {
         match self.root {
             Some(ref root) => Iter::new(root),
             None => Iter {
-                frames: Vec::new(),
+                stack: Vec::new(),
             }
         }
     }
 }
+impl<T: Debug, C: Compare<T> + Default> FromIterator<T> for BoxBstBy<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let elems: Vec<T> = iter.into_iter().collect();
+        let cmp = C::default();
+        if build::check_sorted_by(&elems, &cmp).is_ok() {
+            return Self::build_sorted(elems, cmp);
+        }
+        let mut tree = Self::with_comparator(cmp);
+        for elem in elems {
+            tree.insert(elem);
+        }
+        tree
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Ord + Debug> Shape<T> for BoxBst<T> {
+    fn preorder(&self) -> Vec<Option<&T>> {
+        serialize::preorder(self.root.as_ref())
+    }
+
+    fn from_checked_preorder(tokens: Vec<Option<T>>) -> Self {
+        let len = tokens.iter().filter(|token| token.is_some()).count();
+        let root = serialize::rebuild(tokens, |elem, left, right| BoxBstNode {
+            elem,
+            children: [left.map(Box::new), right.map(Box::new)],
+        }).expect("checked pre-order sequence is malformed");
+        BoxBst {
+            root,
+            len,
+            cmp: Natural,
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Ord + Debug + Send + Sync> BoxBst<T> {
+    /// As `from_sorted`, but checking the order and building the left and right subtrees
+    /// of each node in parallel.
+    pub fn par_from_sorted(elems: Vec<T>) -> Result<Self, NotSorted> {
+        parallel::check_sorted(&elems)?;
+        let len = elems.len();
+        Ok(BoxBst {
+            root: BoxBstNode::par_balanced(&mut parallel::into_options(elems)),
+            len,
+            cmp: Natural,
+        })
+    }
+}
+/// Sorts and deduplicates the new elements in parallel, then splits them by the element of
+/// each node they pass, inserting into its left and right subtrees in parallel. The existing
+/// nodes keep their places, and the new elements which land in the same empty place are built
+/// into a balanced subtree there.
+#[cfg(feature = "rayon")]
+impl<T: Ord + Debug + Send + Sync> ParallelExtend<T> for BoxBst<T> {
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, par_iter: I) {
+        let mut new: Vec<T> = par_iter.into_par_iter().collect();
+        new.par_sort();
+        new.dedup();
+        let mut new = parallel::into_options(new);
+        let inserted = match self.root {
+            Some(ref mut root) => root.par_insert_sorted(&mut new),
+            None => {
+                self.root = BoxBstNode::par_balanced(&mut new);
+                new.len()
+            },
+        };
+        self.len += inserted;
+    }
+}
+#[cfg(feature = "rayon")]
+impl<'s, T: Ord + Debug + Sync> IntoParallelIterator for &'s BoxBst<T> {
+    type Item = &'s T;
+    type Iter = ParIter<'s, T>;
+
+    fn into_par_iter(self) -> ParIter<'s, T> {
+        ParIter(parallel::ParIter::new(self.root.as_ref()))
+    }
+}
+
+#[cfg(feature = "rayon")]
+pub struct ParIter<'t, T: Ord + Debug + 't>(parallel::ParIter<'t, T, &'t BoxBstNode<T>>);
+#[cfg(feature = "rayon")]
+impl<'t, T: Ord + Debug + Sync + 't> ParallelIterator for ParIter<'t, T> {
+    type Item = &'t T;
+
+    fn drive_unindexed<C: UnindexedConsumer<&'t T>>(self, consumer: C) -> C::Result {
+        self.0.drive_unindexed(consumer)
+    }
+}
 
 #[derive(Debug)]
-struct BoxBstNode<T: Ord + Debug> {
+struct BoxBstNode<T: Debug> {
     elem: T,
     children: [Option<Box<BoxBstNode<T>>>; 2],
 }
-impl<T: Ord + Debug> BoxBstNode<T> {
+impl<T: Debug> BoxBstNode<T> {
     fn new(elem: T) -> Self {
         BoxBstNode {
             elem,
@@ -72,8 +324,95 @@ impl<T: Ord + Debug> BoxBstNode<T> {
         }
     }
 
-    fn insert(&mut self, elem: T) -> bool {
-        let recurse_into: usize = match elem.cmp(&self.elem) {
+    // the recursion is only as deep as the balanced tree
+    fn balanced<I: Iterator<Item = T>>(len: usize, elems: &mut I) -> Option<Self> {
+        if len == 0 {
+            return None;
+        }
+        let left_len = build::left_len(len);
+        let left = Self::balanced(left_len, elems);
+        let mut node = Self::new(elems.next().unwrap());
+        node.children[0] = left.map(Box::new);
+        node.children[1] = Self::balanced(len - 1 - left_len, elems).map(Box::new);
+        Some(node)
+    }
+
+    // the recursion is only as deep as the balanced tree
+    #[cfg(feature = "rayon")]
+    fn par_balanced(elems: &mut [Option<T>]) -> Option<Self> where T: Send {
+        if elems.len() <= parallel::SEQUENTIAL_LEN {
+            let len = elems.len();
+            return Self::balanced(len, &mut elems.iter_mut().map(|elem| elem.take().unwrap()));
+        }
+        let left_len = build::left_len(elems.len());
+        let (left, rest) = elems.split_at_mut(left_len);
+        let (elem, right) = rest.split_first_mut().unwrap();
+        let (left, right) = rayon::join(|| Self::par_balanced(left), || Self::par_balanced(right));
+        let mut node = Self::new(elem.take().unwrap());
+        node.children[0] = left.map(Box::new);
+        node.children[1] = right.map(Box::new);
+        Some(node)
+    }
+
+    // insert sorted, distinct elements into the subtree under this node, returning how many
+    // weren't in it already. this walks down in a loop while the elements all go one way, as
+    // keys appended past the end of an ascending chain do, and only recurses where they split
+    // between two existing subtrees, so a degenerate tree can't overflow the stack
+    #[cfg(feature = "rayon")]
+    fn par_insert_sorted(&mut self, elems: &mut [Option<T>]) -> usize where T: Ord + Send {
+        let (mut node, mut elems) = (self, elems);
+        let mut inserted = 0;
+        loop {
+            let split = elems.binary_search_by(|elem| elem.as_ref().unwrap().cmp(&node.elem));
+            let (left_len, right_start) = match split {
+                Ok(i) => (i, i + 1),
+                Err(i) => (i, i),
+            };
+            let (left, rest) = elems.split_at_mut(left_len);
+            let right = &mut rest[right_start - left_len..];
+            let (left_child, right_child) = node.children.split_at_mut(1);
+            let left = Self::par_descend(&mut left_child[0], left, &mut inserted);
+            let right = Self::par_descend(&mut right_child[0], right, &mut inserted);
+            match (left, right) {
+                (Some((left, left_elems)), Some((right, right_elems))) => {
+                    let len = left_elems.len() + right_elems.len();
+                    let (left, right) = if len <= parallel::SEQUENTIAL_LEN {
+                        (left.par_insert_sorted(left_elems), right.par_insert_sorted(right_elems))
+                    } else {
+                        rayon::join(|| left.par_insert_sorted(left_elems),
+                                    || right.par_insert_sorted(right_elems))
+                    };
+                    return inserted + left + right;
+                },
+                (Some((child, child_elems)), None) | (None, Some((child, child_elems))) => {
+                    node = child;
+                    elems = child_elems;
+                },
+                (None, None) => return inserted,
+            }
+        }
+    }
+
+    // the child the elements still have to be inserted into, along with them, or none if
+    // there are no elements, or the child was empty and they were built into a subtree there
+    #[cfg(feature = "rayon")]
+    fn par_descend<'a>(child: &'a mut Option<Box<Self>>, elems: &'a mut [Option<T>],
+                       inserted: &mut usize) -> Option<(&'a mut Self, &'a mut [Option<T>])>
+        where T: Send {
+
+        if elems.is_empty() {
+            return None;
+        }
+        if child.is_none() {
+            *inserted += elems.len();
+            *child = Self::par_balanced(elems).map(Box::new);
+            return None;
+        }
+        child.as_mut().map(|child| (&mut **child, elems))
+    }
+
+    fn insert<C: Compare<T>>(&mut self, elem: T, cmp: &C) -> bool {
+        let recurse_into: usize = match cmp.compare(&elem, &self.elem) {
             Ordering::Equal => {
                 return false;
             },
@@ -81,7 +420,7 @@ impl<T: Ord + Debug> BoxBstNode<T> {
             Ordering::Less => 0,
         };
         match &mut self.children[recurse_into] {
-            &mut Some(ref mut child) => child.insert(elem),
+            &mut Some(ref mut child) => child.insert(elem, cmp),
             None => {
                 self.children[recurse_into] = Some(Box::new(BoxBstNode::new(elem)));
                 true
@@ -89,8 +428,8 @@ impl<T: Ord + Debug> BoxBstNode<T> {
         }
     }
 
-    fn remove(mut self, elem: &T) -> (Option<Self>, bool) {
-        let recurse_into: Option<usize> = match elem.cmp(&self.elem) {
+    fn remove<C: Compare<T>>(mut self, elem: &T, cmp: &C) -> (Option<Self>, bool) {
+        let recurse_into: Option<usize> = match cmp.compare(elem, &self.elem) {
             Ordering::Equal => None,
             Ordering::Greater => Some(1),
             Ordering::Less => Some(0),
@@ -102,7 +441,7 @@ impl<T: Ord + Debug> BoxBstNode<T> {
                 match self.children[branch].take() {
                     Some(child) => {
                         // move the detached child through a recursion of this function
-                        let (new_child, removed) = child.remove(elem);
+                        let (new_child, removed) = child.remove(elem, cmp);
                         if let Some(new_child) = new_child {
                             // if it produced a replacement child, reattach it
                             self.children[branch] = Some(Box::new(new_child));
@@ -169,8 +508,8 @@ impl<T: Ord + Debug> BoxBstNode<T> {
         }
     }
 
-    fn contains(&self, elem: &T) -> bool {
-        let recurse_into: usize = match elem.cmp(&self.elem) {
+    fn contains<C: Compare<T>>(&self, elem: &T, cmp: &C) -> bool {
+        let recurse_into: usize = match cmp.compare(elem, &self.elem) {
             Ordering::Equal => {
                 return true;
             },
@@ -178,90 +517,124 @@ impl<T: Ord + Debug> BoxBstNode<T> {
             Ordering::Less => 0,
         };
         match self.children[recurse_into] {
-            Some(ref child) => child.contains(elem),
+            Some(ref child) => child.contains(elem, cmp),
             None => false,
         }
     }
 }
 
-pub struct Iter<'t, T: Ord + Debug> {
-    frames: Vec<IterFrame<&'t BoxBstNode<T>>>,
+impl<'t, T: Debug> NodeRef<'t, T> for &'t BoxBstNode<T> {
+    fn elem(&self) -> &'t T {
+        &self.elem
+    }
+
+    fn child(&self, branch: usize) -> Option<Self> {
+        self.children[branch].as_ref().map(|child| &**child)
+    }
 }
-#[derive(Copy, Clone)]
-struct IterFrame<E> {
-    elem: E,
-    branch: Option<usize>
+
+pub struct Iter<'t, T: Debug> {
+    // the nodes whose elements are yet to be visited, but whose left subtrees have been
+    // pushed above them
+    stack: Vec<&'t BoxBstNode<T>>,
 }
-impl<'t, T: Ord + Debug> Iter<'t, T> {
+impl<'t, T: Debug> Iter<'t, T> {
     fn new(root: &'t BoxBstNode<T>) -> Self {
-        // initially seek the leftmost node
+        // initially seek the leftmost node, which holds the least element
         let mut iter = Iter {
-            frames: vec![IterFrame {
-                elem: root,
-                branch: None,
-            }],
+            stack: Vec::new(),
         };
-        iter.seek_leftmost();
+        iter.push_leftmost(root);
         iter
     }
 
-    fn seek_leftmost(&mut self) {
-        while match &self.frames.last().unwrap().elem.children[0] {
-            Some(ref left_child) => {
-                self.frames.push(IterFrame {
-                    elem: &*left_child,
-                    branch: Some(0),
-                });
-                true
-            },
-            None => match &self.frames.last().unwrap().elem.children[1] {
-                Some(ref right_child) => {
-                    self.frames.push(IterFrame {
-                        elem: &*right_child,
-                        branch: Some(1),
-                    });
-                    true
-                },
-                None => false
-            }
-        } {}
+    fn push_leftmost(&mut self, mut node: &'t BoxBstNode<T>) {
+        self.stack.push(node);
+        while let Some(ref left_child) = node.children[0] {
+            node = &**left_child;
+            self.stack.push(node);
+        }
     }
 }
-impl<'t, T: Ord + Debug> Iterator for Iter<'t, T> {
+impl<'t, T: Debug> Iterator for Iter<'t, T> {
     type Item = &'t T;
 
     fn next(&mut self) -> Option<&'t T> {
-        match self.frames.last().cloned() {
-            Some(curr_frame) => {
-                // move up, and if we moved up from the left child, seek the leftmost node
-                // of the right subtree
-                match curr_frame.branch {
-                    Some(this_branch_index) => {
-                        self.frames.pop().unwrap();
-                        if this_branch_index == 0 {
-                            match self.frames.last().unwrap().elem.children[1].as_ref() {
-                                Some(right_child) => {
-                                    self.frames.push(IterFrame {
-                                        elem: &*right_child,
-                                        branch: Some(1),
-                                    });
-                                    // only seek the leftmost child if we actually have a right child
-                                    self.seek_leftmost();
-                                },
-                                None => ()
-                            };
-                        }
-                    },
-                    None => {
-                        // however, if we've hit the top, that means that we're done iterating
-                        self.frames.pop().unwrap();
-                        debug_assert_eq!(self.frames.len(), 0);
-                    }
-                };
-
-                Some(&curr_frame.elem.elem)
-            },
-            None => None
+        let curr = self.stack.pop()?;
+        // the next element is the leftmost node of the right subtree, if there is one, or
+        // else the nearest node below on the stack
+        if let Some(ref right_child) = curr.children[1] {
+            self.push_leftmost(&**right_child);
         }
+        Some(&curr.elem)
     }
-}
\ No newline at end of file
+}
+
+// a chain leaning right, as ascending inserts would make, built directly since those
+// inserts would take quadratic time
+#[cfg(test)]
+fn right_chain(elems: Vec<i32>) -> BoxBst<i32> {
+    let len = elems.len();
+    let mut root = None;
+    for elem in elems.into_iter().rev() {
+        root = Some(BoxBstNode {
+            elem,
+            children: [None, root.map(Box::new)],
+        });
+    }
+    BoxBstBy {
+        root,
+        len,
+        cmp: Natural,
+    }
+}
+
+#[test]
+fn clone_copies_degenerate_chain() {
+    let len = 100_000;
+    let tree = right_chain((0..len).collect());
+    assert_eq!(tree.stats().unwrap().height as i32, len);
+
+    let copy = tree.clone();
+    assert_eq!(copy.stats(), tree.stats());
+    assert!(copy.validate().is_ok());
+    assert!((&copy).into_iter().eq(&tree));
+}
+
+#[test]
+fn validate_finds_misplaced_elements() {
+    use std::mem;
+
+    // 2 at the root, with 1 and 3 below it
+    let mut tree = BoxBst::from_sorted(vec![1, 2, 3]).unwrap();
+    {
+        let root = tree.root.as_mut().unwrap();
+        let left = root.children[0].as_mut().unwrap();
+        mem::swap(&mut root.elem, &mut left.elem);
+    }
+    assert_eq!(tree.validate().unwrap_err(), InvariantError::new(vec![],
+               "element 1 is out of order, it comes after 2".to_owned()));
+
+    let mut tree = BoxBst::from_sorted(vec![1, 2, 3]).unwrap();
+    tree.root.as_mut().unwrap().children[1].as_mut().unwrap().elem = 2;
+    assert_eq!(tree.validate().unwrap_err(), InvariantError::new(vec![1],
+               "duplicate element 2".to_owned()));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_extend_walks_degenerate_chain() {
+    // appending past the end of the chain, and into the gaps all along it, once recursed
+    // for every node of the chain
+    let len = 100_000;
+    let mut tree = right_chain((0..len).collect());
+    tree.par_extend((len..2 * len).into_par_iter());
+    assert_eq!(tree.len(), 2 * len as usize);
+    assert!(tree.validate().is_ok());
+
+    let mut tree = right_chain((0..len).map(|i| i * 2).collect());
+    tree.par_extend((0..len).into_par_iter().map(|i| i * 2 + 1));
+    assert_eq!(tree.len(), 2 * len as usize);
+    assert!(tree.validate().is_ok());
+    assert!((&tree).into_iter().cloned().eq(0..2 * len));
+}
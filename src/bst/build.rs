@@ -0,0 +1,37 @@
+use super::compare::{Compare, Natural};
+
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+
+/// Input to a sorted bulk build which wasn't strictly increasing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NotSorted {
+    /// The index of the first element which isn't greater than the one before it.
+    pub index: usize,
+}
+impl Display for NotSorted {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "element {} is not greater than the element before it", self.index)
+    }
+}
+
+/// Check that the elements are strictly increasing, so sorted and free of duplicates.
+pub fn check_sorted<T: Ord>(elems: &[T]) -> Result<(), NotSorted> {
+    check_sorted_by(elems, &Natural)
+}
+
+/// As `check_sorted`, but in the comparator's ordering.
+pub fn check_sorted_by<T, C: Compare<T>>(elems: &[T], cmp: &C) -> Result<(), NotSorted> {
+    match elems.windows(2).position(|pair| cmp.compare(&pair[0], &pair[1]) != Ordering::Less) {
+        Some(i) => Err(NotSorted {
+            index: i + 1,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The number of elements which go in the left subtree of a balanced tree of `len`
+/// elements, the rest after the root going in the right.
+pub fn left_len(len: usize) -> usize {
+    (len - 1) / 2
+}
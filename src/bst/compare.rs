@@ -0,0 +1,139 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+
+/// An ordering on elements, which a tree uses in place of `Ord`.
+pub trait Compare<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// The element type's own `Ord` ordering.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Natural;
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// The opposite of another comparator's ordering.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Reverse<C = Natural>(pub C);
+impl<T: ?Sized, C: Compare<T>> Compare<T> for Reverse<C> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(b, a)
+    }
+}
+
+/// Orders elements by a key projected out of each one. Elements with equal keys are
+/// considered equal, so a tree holds at most one of them.
+#[derive(Copy, Clone)]
+pub struct ByKey<F, K> {
+    key: F,
+    _key: PhantomData<fn() -> K>,
+}
+impl<F, K> ByKey<F, K> {
+    pub fn new(key: F) -> Self {
+        ByKey {
+            key,
+            _key: PhantomData,
+        }
+    }
+}
+impl<T: ?Sized, F: Fn(&T) -> K, K: Ord> Compare<T> for ByKey<F, K> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.key)(a).cmp(&(self.key)(b))
+    }
+}
+impl<F, K> Debug for ByKey<F, K> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("ByKey")
+    }
+}
+
+/// Orders strings by their lowercase forms, so strings differing only in case are equal.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CaseInsensitive;
+impl<T: AsRef<str> + ?Sized> Compare<T> for CaseInsensitive {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        let a = a.as_ref().chars().flat_map(char::to_lowercase);
+        let b = b.as_ref().chars().flat_map(char::to_lowercase);
+        a.cmp(b)
+    }
+}
+
+#[test]
+fn comparators_order_trees() {
+    use super::Bst;
+    use super::bonzai::BonzaiBstBy;
+    use super::boxy::BoxBstBy;
+
+    let words = ["pear", "Apple", "fig", "apple", "Fig", "banana"];
+
+    let mut bonzai: BonzaiBstBy<&str, CaseInsensitive> = BonzaiBstBy::new();
+    let mut boxy: BoxBstBy<&str, Reverse<CaseInsensitive>> = BoxBstBy::new();
+    for &word in &words {
+        bonzai.insert(word);
+        boxy.insert(word);
+    }
+    assert_eq!((&bonzai).into_iter().cloned().collect::<Vec<_>>(), ["Apple", "banana", "fig", "pear"]);
+    assert_eq!((&boxy).into_iter().cloned().collect::<Vec<_>>(), ["pear", "fig", "banana", "Apple"]);
+    assert!(bonzai.contains(&"FIG") && boxy.contains(&"PEAR"));
+    assert!(bonzai.validate().is_ok() && boxy.validate().is_ok());
+
+    // closures have no default, so the tree is given its comparator
+    let by_len = ByKey::new(|word: &&str| word.len());
+    let mut bonzai = BonzaiBstBy::with_comparator(by_len);
+    let mut boxy = BoxBstBy::with_comparator(by_len);
+    for &word in &words {
+        bonzai.insert(word);
+        boxy.insert(word);
+    }
+    assert_eq!((&bonzai).into_iter().cloned().collect::<Vec<_>>(), ["fig", "pear", "Apple", "banana"]);
+    assert_eq!((&boxy).into_iter().cloned().collect::<Vec<_>>(), ["fig", "pear", "Apple", "banana"]);
+    assert!(bonzai.remove(&"kiwi") && boxy.remove(&"plum"));
+    assert_eq!(bonzai.len() + boxy.len(), 6);
+}
+
+#[test]
+fn key_projection_orders_non_ord_elements() {
+    use super::bonzai::BonzaiBstBy;
+    use super::boxy::BoxBstBy;
+    use super::pretty::PrettyConfig;
+
+    // the weight keeps the struct from being `Ord`, so only the key orders it
+    #[derive(Debug, Clone, PartialEq)]
+    struct Fruit {
+        name: &'static str,
+        weight: f64,
+    }
+    let fruit = |name, weight| Fruit { name, weight };
+    let by_name = ByKey::new(|fruit: &Fruit| fruit.name);
+    let basket = vec![fruit("pear", 0.2), fruit("apple", 0.15), fruit("fig", 0.05),
+                      fruit("apple", 0.3), fruit("banana", 0.12)];
+
+    let mut bonzai = BonzaiBstBy::with_comparator(by_name);
+    let mut boxy = BoxBstBy::with_comparator(by_name);
+    for f in &basket {
+        bonzai.insert(f.clone());
+        boxy.insert(f.clone());
+    }
+    let names: Vec<&str> = (&bonzai).into_iter().map(|f| f.name).collect();
+    assert_eq!(names, ["apple", "banana", "fig", "pear"]);
+    // the second apple has an equal key, so the first one is kept
+    assert_eq!((&bonzai).into_iter().next(), Some(&fruit("apple", 0.15)));
+    assert!((&boxy).into_iter().eq((&bonzai).into_iter()));
+    assert!(bonzai.validate().is_ok() && boxy.validate().is_ok());
+    assert_eq!(bonzai.stats(), boxy.stats());
+    assert_eq!(bonzai.to_dot(None), boxy.to_dot(None));
+    assert_eq!(bonzai.pretty(&PrettyConfig::default()), boxy.pretty(&PrettyConfig::default()));
+    assert_eq!(bonzai.snapshot().len(), 4);
+
+    let sorted: Vec<Fruit> = (&bonzai).into_iter().cloned().collect();
+    let balanced = BoxBstBy::from_sorted_by(sorted.clone(), by_name).unwrap();
+    assert_eq!(balanced.stats().unwrap().height, 3);
+    assert!(balanced.validate().is_ok());
+    let mut reversed = sorted;
+    reversed.reverse();
+    assert_eq!(BonzaiBstBy::from_sorted_by(reversed, by_name).err().unwrap().index, 1);
+}
@@ -0,0 +1,152 @@
+//! Trees shared between threads, behind readers-writer locks.
+
+use super::Bst;
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::{RwLock, RwLockReadGuard};
+
+/// Any `Bst` behind one readers-writer lock, so that lookups run in parallel, but each
+/// change excludes every other operation.
+///
+/// A thread which panics while changing the tree may leave it broken, so the lock is then
+/// poisoned, and every later operation panics.
+#[derive(Debug)]
+pub struct ConcurrentBst<T, B> {
+    tree: RwLock<B>,
+    _elem: PhantomData<fn(T)>,
+}
+impl<T: Ord + Debug, B: Bst<T>> ConcurrentBst<T, B>
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    pub fn new() -> Self {
+        Self::from_tree(B::new())
+    }
+
+    pub fn from_tree(tree: B) -> Self {
+        ConcurrentBst {
+            tree: RwLock::new(tree),
+            _elem: PhantomData,
+        }
+    }
+
+    pub fn insert(&self, elem: T) -> bool {
+        self.tree.write().unwrap().insert(elem)
+    }
+
+    pub fn remove(&self, elem: &T) -> bool {
+        self.tree.write().unwrap().remove(elem)
+    }
+
+    pub fn contains(&self, elem: &T) -> bool {
+        self.tree.read().unwrap().contains(elem)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hold the read lock, for iteration or anything else that needs a consistent view.
+    pub fn read(&self) -> RwLockReadGuard<B> {
+        self.tree.read().unwrap()
+    }
+
+    pub fn into_inner(self) -> B {
+        self.tree.into_inner().unwrap()
+    }
+}
+impl<T: Ord + Debug, B: Bst<T>> Default for ConcurrentBst<T, B>
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Several `ConcurrentBst`s, each holding the elements which hash to it, so that changes
+/// to different shards don't contend.
+///
+/// Partitioning by hash spreads any key distribution evenly, but scatters neighbouring keys,
+/// so there's no ordered iteration across shards.
+#[derive(Debug)]
+pub struct ShardedBst<T, B> {
+    shards: Vec<ConcurrentBst<T, B>>,
+}
+impl<T: Ord + Debug + Hash, B: Bst<T>> ShardedBst<T, B>
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    pub fn new(num_shards: usize) -> Self {
+        assert!(num_shards > 0, "sharded tree with zero shards");
+        ShardedBst {
+            shards: (0..num_shards).map(|_| ConcurrentBst::new()).collect(),
+        }
+    }
+
+    fn shard(&self, elem: &T) -> &ConcurrentBst<T, B> {
+        let mut hasher = DefaultHasher::new();
+        elem.hash(&mut hasher);
+        &self.shards[(hasher.finish() % self.shards.len() as u64) as usize]
+    }
+
+    pub fn insert(&self, elem: T) -> bool {
+        self.shard(&elem).insert(elem)
+    }
+
+    pub fn remove(&self, elem: &T) -> bool {
+        self.shard(elem).remove(elem)
+    }
+
+    pub fn contains(&self, elem: &T) -> bool {
+        self.shard(elem).contains(elem)
+    }
+
+    /// The total over every shard, which is only a snapshot if nothing is changing.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn shards(&self) -> &[ConcurrentBst<T, B>] {
+        &self.shards
+    }
+}
+
+#[test]
+fn threads_share_one_tree() {
+    use super::bonzai::BonzaiBst;
+    use std::sync::Arc;
+    use std::thread;
+
+    let locked: Arc<ConcurrentBst<i32, BonzaiBst<i32>>> = Arc::new(ConcurrentBst::new());
+    let sharded: Arc<ShardedBst<i32, BonzaiBst<i32>>> = Arc::new(ShardedBst::new(3));
+    let threads: Vec<_> = (0..4).map(|t| {
+        let (locked, sharded) = (locked.clone(), sharded.clone());
+        thread::spawn(move || {
+            // each thread owns the keys congruent to it mod 4, and keeps the ones congruent to
+            // it mod 8, so the keys left are those below 4 mod 8
+            for key in (0..400).filter(|key| key % 4 == t) {
+                assert!(locked.insert(key));
+                assert!(sharded.insert(key));
+            }
+            for key in (0..400).filter(|key| key % 4 == t && key % 8 != t) {
+                assert!(locked.remove(&key));
+                assert!(sharded.remove(&key));
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert!(locked.read().into_iter().eq((0..400).filter(|key| key % 8 < 4).collect::<Vec<_>>().iter()));
+    assert_eq!(sharded.len(), 200);
+    assert!(sharded.shards().iter().all(|shard| shard.read().validate().is_ok()));
+}
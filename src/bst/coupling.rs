@@ -0,0 +1,252 @@
+//! A concurrent tree with a lock per node, traversed hand over hand.
+//!
+//! Every traversal locks a node's child before unlocking the node, so threads move down the
+//! tree in order and can't overtake one another, and operations in disjoint subtrees run in
+//! parallel. `contains` takes only read locks. `insert` also descends with read locks, then
+//! write locks just the node it attaches to, checking that the node wasn't removed in
+//! between. `remove` write locks all the way down, since it restructures around the node.
+//!
+//! This can't be built on bonzai: its trees are changed through a `TreeOperation`, which
+//! borrows the whole tree mutably, so there's no way to lock or change one node while other
+//! threads are in other nodes. The nodes here are reference counted, and each has its own
+//! lock, which costs an allocation and a lock per node, and a lock round trip per level.
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::mem;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+type Link<T> = Option<Arc<RwLock<Node<T>>>>;
+
+#[derive(Debug)]
+struct Node<T> {
+    elem: T,
+    children: [Link<T>; 2],
+    /// Set when the node is unlinked, so that an insert which let go of it can tell.
+    removed: bool,
+}
+
+fn new_link<T>(elem: T) -> Link<T> {
+    Some(Arc::new(RwLock::new(Node {
+        elem,
+        children: [None, None],
+        removed: false,
+    })))
+}
+
+// the locked link being followed, which is either the root or a node's child
+
+enum ReadParent<'a, T: 'a> {
+    Root(RwLockReadGuard<'a, Link<T>>),
+    Node(&'a RwLock<Node<T>>, RwLockReadGuard<'a, Node<T>>, usize),
+}
+impl<'a, T> ReadParent<'a, T> {
+    fn link(&self) -> &Link<T> {
+        match *self {
+            ReadParent::Root(ref root) => &**root,
+            ReadParent::Node(_, ref node, branch) => &node.children[branch],
+        }
+    }
+}
+
+enum WriteParent<'a, T: 'a> {
+    Root(RwLockWriteGuard<'a, Link<T>>),
+    Node(RwLockWriteGuard<'a, Node<T>>, usize),
+    /// A link in a node which a caller further up holds locked.
+    Held(&'a mut Link<T>),
+}
+impl<'a, T> WriteParent<'a, T> {
+    fn link(&mut self) -> &mut Link<T> {
+        match *self {
+            WriteParent::Root(ref mut root) => &mut **root,
+            WriteParent::Node(ref mut node, branch) => &mut node.children[branch],
+            WriteParent::Held(ref mut link) => &mut **link,
+        }
+    }
+}
+
+fn branch<T: Ord>(elem: &T, node: &T) -> Option<usize> {
+    match elem.cmp(node) {
+        Ordering::Equal => None,
+        Ordering::Less => Some(0),
+        Ordering::Greater => Some(1),
+    }
+}
+
+#[derive(Debug)]
+pub struct LockCouplingBst<T> {
+    root: RwLock<Link<T>>,
+    len: AtomicUsize,
+}
+impl<T: Ord + Debug> LockCouplingBst<T> {
+    pub fn new() -> Self {
+        LockCouplingBst {
+            root: RwLock::new(None),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn contains(&self, elem: &T) -> bool {
+        contains_below(ReadParent::Root(self.root.read().unwrap()), elem)
+    }
+
+    pub fn insert(&self, elem: T) -> bool {
+        let mut elem = elem;
+        loop {
+            match insert_below(ReadParent::Root(self.root.read().unwrap()), &self.root, elem) {
+                Inserted::Done(inserted) => {
+                    if inserted {
+                        self.len.fetch_add(1, AtomicOrdering::SeqCst);
+                    }
+                    return inserted;
+                },
+                // the node to attach to was removed meanwhile, so start again from the top
+                Inserted::Retry(returned) => elem = returned,
+            }
+        }
+    }
+
+    pub fn remove(&self, elem: &T) -> bool {
+        let removed = remove_below(WriteParent::Root(self.root.write().unwrap()), elem);
+        if removed {
+            self.len.fetch_sub(1, AtomicOrdering::SeqCst);
+        }
+        removed
+    }
+
+    /// The number of elements, which may lag behind changes still in progress.
+    pub fn len(&self) -> usize {
+        self.len.load(AtomicOrdering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The elements in order. Other threads' changes during the walk may or may not be seen.
+    pub fn elems(&self) -> Vec<T> where T: Clone {
+        let mut elems = Vec::new();
+        let mut stack: Vec<Arc<RwLock<Node<T>>>> = Vec::new();
+        let mut curr = self.root.read().unwrap().clone();
+        loop {
+            while let Some(node) = curr {
+                curr = node.read().unwrap().children[0].clone();
+                stack.push(node);
+            }
+            match stack.pop() {
+                Some(node) => {
+                    let node = node.read().unwrap();
+                    elems.push(node.elem.clone());
+                    curr = node.children[1].clone();
+                },
+                None => return elems,
+            }
+        }
+    }
+}
+impl<T: Ord + Debug> Default for LockCouplingBst<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// each of these recurses once per level, and so is only as deep as the tree
+
+fn contains_below<T: Ord>(parent: ReadParent<T>, elem: &T) -> bool {
+    let node = match *parent.link() {
+        Some(ref node) => node.clone(),
+        None => return false,
+    };
+    let guard = node.read().unwrap();
+    drop(parent);
+    match branch(elem, &guard.elem) {
+        None => true,
+        Some(b) => contains_below(ReadParent::Node(&node, guard, b), elem),
+    }
+}
+
+enum Inserted<T> {
+    Done(bool),
+    Retry(T),
+}
+
+fn insert_below<T: Ord>(parent: ReadParent<T>, root: &RwLock<Link<T>>, elem: T) -> Inserted<T> {
+    let node = match *parent.link() {
+        Some(ref node) => node.clone(),
+        None => {
+            // nothing to descend into, so relock the parent for writing, and check that
+            // the element still belongs in the empty link
+            return match parent {
+                ReadParent::Root(guard) => {
+                    drop(guard);
+                    let mut guard = root.write().unwrap();
+                    if guard.is_some() {
+                        return Inserted::Retry(elem);
+                    }
+                    *guard = new_link(elem);
+                    Inserted::Done(true)
+                },
+                ReadParent::Node(node, guard, b) => {
+                    drop(guard);
+                    let mut guard = node.write().unwrap();
+                    // a node which isn't removed stays where it is, but its element may
+                    // have been replaced, and its children changed
+                    if guard.removed {
+                        return Inserted::Retry(elem);
+                    }
+                    match branch(&elem, &guard.elem) {
+                        None => Inserted::Done(false),
+                        Some(now) if now == b && guard.children[b].is_none() => {
+                            guard.children[b] = new_link(elem);
+                            Inserted::Done(true)
+                        },
+                        Some(_) => Inserted::Retry(elem),
+                    }
+                },
+            };
+        },
+    };
+    let guard = node.read().unwrap();
+    drop(parent);
+    match branch(&elem, &guard.elem) {
+        None => Inserted::Done(false),
+        Some(b) => insert_below(ReadParent::Node(&node, guard, b), root, elem),
+    }
+}
+
+fn remove_below<T: Ord>(mut parent: WriteParent<T>, elem: &T) -> bool {
+    let node = match *parent.link() {
+        Some(ref node) => node.clone(),
+        None => return false,
+    };
+    let mut guard = node.write().unwrap();
+    if let Some(b) = branch(elem, &guard.elem) {
+        drop(parent);
+        return remove_below(WriteParent::Node(guard, b), elem);
+    }
+    // this is the node, and the parent is still locked, so it can be unlinked
+    if guard.children[0].is_some() && guard.children[1].is_some() {
+        // keep the node, but swap its element with the least of the right subtree, whose
+        // node is unlinked instead
+        let Node { ref mut elem, ref mut children, .. } = *guard;
+        remove_least(WriteParent::Held(&mut children[1]), elem);
+    } else {
+        let child = guard.children[0].take().or_else(|| guard.children[1].take());
+        guard.removed = true;
+        *parent.link() = child;
+    }
+    true
+}
+
+fn remove_least<T>(mut parent: WriteParent<T>, swap_into: &mut T) {
+    let node = parent.link().clone().unwrap();
+    let mut guard = node.write().unwrap();
+    if guard.children[0].is_some() {
+        drop(parent);
+        return remove_least(WriteParent::Node(guard, 0), swap_into);
+    }
+    mem::swap(swap_into, &mut guard.elem);
+    guard.removed = true;
+    *parent.link() = guard.children[1].take();
+}
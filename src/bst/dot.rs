@@ -0,0 +1,142 @@
+use super::shape::NodeRef;
+
+use std::fmt::Debug;
+use std::str::FromStr;
+
+/// Extra information to label each node with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Annotation {
+    /// The height of the subtree rooted at the node, so 1 for a leaf.
+    Height,
+    /// The number of nodes in the subtree rooted at the node.
+    Size,
+}
+impl FromStr for Annotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "height" => Ok(Annotation::Height),
+            "size" => Ok(Annotation::Size),
+            other => Err(format!("unknown annotation: {} (expected height or size)", other)),
+        }
+    }
+}
+
+/// Render the tree below `root` as a graphviz digraph, with each edge labelled L or R and
+/// each empty child slot drawn as a point, so that the sides of single children are clear.
+pub fn to_dot<'t, T: Debug + 't, N: NodeRef<'t, T>>(root: Option<N>,
+                                                     annotation: Option<Annotation>) -> String {
+    // number the nodes in pre-order, so that every child comes after its parent
+    let mut labels: Vec<String> = Vec::new();
+    let mut children: Vec<[Option<usize>; 2]> = Vec::new();
+    let mut stack: Vec<(N, Option<(usize, usize)>)> = root.into_iter()
+        .map(|root| (root, None))
+        .collect();
+    while let Some((node, parent)) = stack.pop() {
+        let id = labels.len();
+        labels.push(escape(&format!("{:?}", node.elem())));
+        children.push([None, None]);
+        if let Some((parent, branch)) = parent {
+            children[parent][branch] = Some(id);
+        }
+        for branch in (0..2).rev() {
+            if let Some(child) = node.child(branch) {
+                stack.push((child, Some((id, branch))));
+            }
+        }
+    }
+
+    // so heights and sizes can be filled in from the last node back to the root
+    let mut heights = vec![0; labels.len()];
+    let mut sizes = vec![0; labels.len()];
+    for id in (0..labels.len()).rev() {
+        let (mut height, mut size) = (0, 0);
+        for child in children[id].iter().filter_map(|&child| child) {
+            height = height.max(heights[child]);
+            size += sizes[child];
+        }
+        heights[id] = height + 1;
+        sizes[id] = size + 1;
+    }
+
+    let mut dot = String::from("digraph bst {\n");
+    dot.push_str("    node [shape=circle];\n");
+    for (id, label) in labels.iter().enumerate() {
+        let label = match annotation {
+            Some(Annotation::Height) => format!("{}\\nh={}", label, heights[id]),
+            Some(Annotation::Size) => format!("{}\\nn={}", label, sizes[id]),
+            None => label.clone(),
+        };
+        dot.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+    }
+    for (id, slots) in children.iter().enumerate() {
+        for (branch, &slot) in slots.iter().enumerate() {
+            let side = if branch == 0 { "L" } else { "R" };
+            match slot {
+                Some(child) => {
+                    dot.push_str(&format!("    n{} -> n{} [label=\"{}\"];\n", id, child, side));
+                },
+                None => {
+                    dot.push_str(&format!("    null{}{} [shape=point];\n", id, side));
+                    dot.push_str(&format!("    n{} -> null{}{} [label=\"{}\"];\n",
+                                          id, id, side, side));
+                },
+            };
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[test]
+fn dot_marks_sides_and_annotates() {
+    use super::Bst;
+    use super::boxy::BoxBst;
+
+    // 2 at the root, 1 and 3 below it, and 4 right of 3, so 3 has a single child
+    let mut tree: BoxBst<i32> = BoxBst::new();
+    for &elem in &[2, 1, 3, 4] {
+        tree.insert(elem);
+    }
+    let plain = tree.to_dot(None).unwrap();
+    assert_eq!(plain, r#"digraph bst {
+    node [shape=circle];
+    n0 [label="2"];
+    n1 [label="1"];
+    n2 [label="3"];
+    n3 [label="4"];
+    n0 -> n1 [label="L"];
+    n0 -> n2 [label="R"];
+    null1L [shape=point];
+    n1 -> null1L [label="L"];
+    null1R [shape=point];
+    n1 -> null1R [label="R"];
+    null2L [shape=point];
+    n2 -> null2L [label="L"];
+    n2 -> n3 [label="R"];
+    null3L [shape=point];
+    n3 -> null3L [label="L"];
+    null3R [shape=point];
+    n3 -> null3R [label="R"];
+}
+"#);
+
+    // only the node labels change, gaining the annotation on a second line
+    let annotated = |labels: &[(&str, &str)]| {
+        labels.iter().fold(plain.clone(), |dot, &(elem, annotation)| {
+            dot.replace(&format!("[label=\"{}\"]", elem),
+                        &format!("[label=\"{}\\n{}\"]", elem, annotation))
+        })
+    };
+    assert_eq!(tree.to_dot(Some(Annotation::Height)).unwrap(),
+               annotated(&[("2", "h=3"), ("1", "h=1"), ("3", "h=2"), ("4", "h=1")]));
+    assert_eq!(tree.to_dot(Some(Annotation::Size)).unwrap(),
+               annotated(&[("2", "n=4"), ("1", "n=1"), ("3", "n=2"), ("4", "n=1")]));
+    assert_eq!(BoxBst::<i32>::new().to_dot(None).unwrap(),
+               "digraph bst {\n    node [shape=circle];\n}\n");
+}
@@ -0,0 +1,445 @@
+//! A tree which survives restarts, by logging each change before applying it. Behind the
+//! `serde` feature.
+//!
+//! A directory holds two files. `snapshot` is the tree at some point, as json through its
+//! serde impls, so as its elements in sorted order, and `log` is every insert and remove
+//! since then, as records of:
+//!
+//! ```text
+//! tag: u8 (0 insert, 1 remove), len: u32, elem as json: [u8; len], crc32 of the rest: u32
+//! ```
+//!
+//! with integers little endian. Opening loads the snapshot and replays the log. A record
+//! cut short or garbled by a crash can only be at the end of the log, since records are only
+//! ever appended, and a failed append is cut off again, so replay stops at a record which
+//! runs past the end of the log, or whose checksum doesn't match with no whole record after
+//! it, and truncates the log there. Any other bad record was damaged in place, so rather
+//! than drop it and every record after it, opening fails.
+
+use super::Bst;
+use benchmark::Op;
+
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+const SNAPSHOT: &str = "snapshot";
+const LOG: &str = "log";
+const INSERT: u8 = 0;
+const REMOVE: u8 = 1;
+// the tag and length before a record's element
+const RECORD_HEAD: usize = 5;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (!(crc & 1)).wrapping_add(1));
+        }
+    }
+    !crc
+}
+
+fn get_u32(bytes: &[u8]) -> u32 {
+    let mut word = [0; 4];
+    word.copy_from_slice(bytes);
+    u32::from_le_bytes(word)
+}
+
+fn encode<T: Serialize>(op: &Op<T>) -> io::Result<Vec<u8>> {
+    let (tag, elem) = match *op {
+        Op::Insert(ref elem) => (INSERT, elem),
+        Op::Remove(ref elem) => (REMOVE, elem),
+        Op::Contains(_) => panic!("contains doesn't change the tree, so isn't logged"),
+    };
+    let elem = serde_json::to_vec(elem)?;
+    let mut record = Vec::with_capacity(RECORD_HEAD + elem.len() + 4);
+    record.push(tag);
+    record.extend_from_slice(&(elem.len() as u32).to_le_bytes());
+    record.extend_from_slice(&elem);
+    let crc = crc32(&record);
+    record.extend_from_slice(&crc.to_le_bytes());
+    Ok(record)
+}
+
+// how much of the record at the start of some bytes is there
+enum Frame {
+    // the bytes end before the record does
+    Short,
+    // the record is all there, and this long, but its checksum doesn't match
+    Garbled(usize),
+    // the record is all there, and this long, and its checksum matches
+    Whole(usize),
+}
+
+fn frame(bytes: &[u8]) -> Frame {
+    if bytes.len() < RECORD_HEAD {
+        return Frame::Short;
+    }
+    let len = RECORD_HEAD + get_u32(&bytes[1..RECORD_HEAD]) as usize + 4;
+    if bytes.len() < len {
+        return Frame::Short;
+    }
+    let (body, crc) = bytes[..len].split_at(len - 4);
+    if crc32(body) != get_u32(crc) {
+        return Frame::Garbled(len);
+    }
+    Frame::Whole(len)
+}
+
+// whether a whole record comes anywhere after a garbled one, following the lengths of any
+// other garbled records in between
+fn whole_record_follows(mut bytes: &[u8]) -> bool {
+    loop {
+        match frame(bytes) {
+            Frame::Short => return false,
+            Frame::Garbled(len) => bytes = &bytes[len..],
+            Frame::Whole(_) => return true,
+        }
+    }
+}
+
+// what replay finds at the start of the bytes it hasn't replayed yet
+enum Decoded<T> {
+    // a whole record, and its length
+    Record(Op<T>, usize),
+    // nothing, or a record cut short or garbled, as a crash mid-append leaves
+    Torn,
+    // a record damaged in place: garbled with whole records after it, which a crash can't
+    // leave since only the last append can be cut short, or checksummed but not holding an op
+    Corrupt(String),
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Decoded<T> {
+    let len = match frame(bytes) {
+        Frame::Short => return Decoded::Torn,
+        Frame::Garbled(len) if whole_record_follows(&bytes[len..]) => {
+            return Decoded::Corrupt("checksum doesn't match".to_owned());
+        },
+        Frame::Garbled(_) => return Decoded::Torn,
+        Frame::Whole(len) => len,
+    };
+    let body = &bytes[..len - 4];
+    let elem = |body: &[u8]| serde_json::from_slice(&body[RECORD_HEAD..]);
+    let op = match body[0] {
+        INSERT => elem(body).map(Op::Insert),
+        REMOVE => elem(body).map(Op::Remove),
+        tag => return Decoded::Corrupt(format!("unknown tag {}", tag)),
+    };
+    match op {
+        Ok(op) => Decoded::Record(op, len),
+        Err(e) => Decoded::Corrupt(format!("element doesn't deserialize: {}", e)),
+    }
+}
+
+// make the renames in `dir` durable
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DurableConfig {
+    /// Take a snapshot, and empty the log, after this many logged changes.
+    pub snapshot_every: usize,
+    /// Flush each record to disk before applying it. Without this, a change survives the
+    /// process crashing, but not the machine.
+    pub sync: bool,
+}
+impl Default for DurableConfig {
+    fn default() -> Self {
+        DurableConfig {
+            snapshot_every: 10_000,
+            sync: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DurableBst<T: Ord + Debug, B: Bst<T>>
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    tree: B,
+    dir: PathBuf,
+    // opened to append, so every write goes to the end, wherever a truncation left it
+    log: File,
+    log_len: u64,
+    logged: usize,
+    failed_snapshot: Option<io::Error>,
+    config: DurableConfig,
+    _elem: PhantomData<T>,
+}
+impl<T, B> DurableBst<T, B>
+    where T: Ord + Debug + Clone + Serialize + DeserializeOwned,
+          B: Bst<T> + Serialize + DeserializeOwned,
+          for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    /// Open the tree stored in `dir`, creating an empty one if there isn't one.
+    pub fn open<P: AsRef<Path>>(dir: P, config: DurableConfig) -> io::Result<Self> {
+        let dir = dir.as_ref().to_owned();
+        fs::create_dir_all(&dir)?;
+
+        let mut tree = if dir.join(SNAPSHOT).exists() {
+            serde_json::from_reader(BufReader::new(File::open(dir.join(SNAPSHOT))?))?
+        } else {
+            B::new()
+        };
+
+        let log = OpenOptions::new().append(true).create(true).open(dir.join(LOG))?;
+        let bytes = fs::read(dir.join(LOG))?;
+        let mut logged = 0;
+        let mut end = 0;
+        loop {
+            let (op, len) = match decode(&bytes[end..]) {
+                Decoded::Record(op, len) => (op, len),
+                Decoded::Torn => break,
+                Decoded::Corrupt(why) => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("log record at byte {} is corrupt: {}", end, why))),
+            };
+            match op {
+                Op::Insert(elem) => { tree.insert(elem); },
+                Op::Remove(elem) => { tree.remove(&elem); },
+                Op::Contains(_) => unreachable!(),
+            };
+            logged += 1;
+            end += len;
+        }
+        if end < bytes.len() {
+            log.set_len(end as u64)?;
+        }
+
+        Ok(DurableBst {
+            tree,
+            dir,
+            log,
+            log_len: end as u64,
+            logged,
+            failed_snapshot: None,
+            config,
+            _elem: PhantomData,
+        })
+    }
+
+    fn append(&mut self, op: Op<T>) -> io::Result<()> {
+        let record = encode(&op)?;
+        let sync = self.config.sync;
+        let log = &mut self.log;
+        let written = log.write_all(&record)
+            .and_then(|()| if sync { log.sync_data() } else { Ok(()) });
+        if let Err(e) = written {
+            // cut off any part of the record which was written, since replay stops at a torn
+            // record and would drop every record appended after it
+            self.log.set_len(self.log_len)?;
+            return Err(e);
+        }
+        self.log_len += record.len() as u64;
+        self.logged += 1;
+        Ok(())
+    }
+
+    // the change is already logged and applied, so a failed snapshot doesn't fail it. the
+    // log is left as it was, and the snapshot is taken again after the next change
+    fn after_change(&mut self) {
+        if self.logged >= self.config.snapshot_every {
+            self.failed_snapshot = self.snapshot().err();
+        }
+    }
+
+    /// Log the insert, then apply it. An error means the insert wasn't logged, or applied.
+    pub fn insert(&mut self, elem: T) -> io::Result<bool> {
+        self.append(Op::Insert(elem.clone()))?;
+        let inserted = self.tree.insert(elem);
+        self.after_change();
+        Ok(inserted)
+    }
+
+    /// Log the remove, then apply it. An error means the remove wasn't logged, or applied.
+    pub fn remove(&mut self, elem: &T) -> io::Result<bool> {
+        self.append(Op::Remove(elem.clone()))?;
+        let removed = self.tree.remove(elem);
+        self.after_change();
+        Ok(removed)
+    }
+
+    pub fn contains(&self, elem: &T) -> bool {
+        self.tree.contains(elem)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn tree(&self) -> &B {
+        &self.tree
+    }
+
+    /// Why the last snapshot taken after a change failed, if it did. It's cleared once a
+    /// snapshot succeeds.
+    pub fn failed_snapshot(&self) -> Option<&io::Error> {
+        self.failed_snapshot.as_ref()
+    }
+
+    /// Write the whole tree as a new snapshot, then empty the log.
+    ///
+    /// The new snapshot replaces the old one by rename, so there's always a whole snapshot
+    /// on disk, and the rename is synced before the log is emptied, so the log is never
+    /// emptied onto the old snapshot. A crash before the log is emptied replays it onto the
+    /// new snapshot, which already includes it, but that's harmless: after any run of
+    /// inserts and removes, an element is present exactly when the last of them to touch it
+    /// was an insert.
+    pub fn snapshot(&mut self) -> io::Result<()> {
+        let tmp = self.dir.join(format!("{}.tmp", SNAPSHOT));
+        {
+            let mut file = BufWriter::new(File::create(&tmp)?);
+            serde_json::to_writer(&mut file, &self.tree)?;
+            file.flush()?;
+            file.get_ref().sync_all()?;
+        }
+        fs::rename(&tmp, self.dir.join(SNAPSHOT))?;
+        sync_dir(&self.dir)?;
+        self.log.set_len(0)?;
+        self.log_len = 0;
+        self.logged = 0;
+        self.log.sync_all()
+    }
+}
+
+#[test]
+fn durable_recovers() {
+    use super::boxy::BoxBst;
+    use std::collections::BTreeSet;
+    use std::env;
+
+    let dir = env::temp_dir().join(format!("bonzai-nbst-durable-{}", ::std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let config = DurableConfig { snapshot_every: 100, sync: false };
+    let mut model = BTreeSet::new();
+    let mut before_last = model.clone();
+    {
+        let mut tree: DurableBst<u32, BoxBst<u32>> = DurableBst::open(&dir, config).unwrap();
+        for n in 0..250u32 {
+            let key = n * 37 % 101;
+            before_last = model.clone();
+            if n % 3 == 0 {
+                assert_eq!(tree.remove(&key).unwrap(), model.remove(&key));
+            } else {
+                assert_eq!(tree.insert(key).unwrap(), model.insert(key));
+            }
+        }
+    }
+    // tear the last record, which must then be dropped, along with the change it logged
+    let log = dir.join(LOG);
+    let len = fs::metadata(&log).unwrap().len();
+    let last = encode(&Op::Remove(249 * 37 % 101u32)).unwrap().len() as u64;
+    OpenOptions::new().write(true).open(&log).unwrap().set_len(len - 2).unwrap();
+    let tree: DurableBst<u32, BoxBst<u32>> = DurableBst::open(&dir, config).unwrap();
+    assert!(tree.tree().into_iter().eq(before_last.iter()));
+    assert_eq!(fs::metadata(&log).unwrap().len(), len - last);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn corrupt_records_fail_open() {
+    use super::boxy::BoxBst;
+    use std::env;
+
+    let dir = env::temp_dir().join(format!("bonzai-nbst-corrupt-log-{}", ::std::process::id()));
+    let config = DurableConfig::default();
+    // records with a matching checksum, but an unknown tag or an element of the wrong type
+    let checksummed = |tag: u8, elem: &[u8]| {
+        let mut record = vec![tag];
+        record.extend_from_slice(&(elem.len() as u32).to_le_bytes());
+        record.extend_from_slice(elem);
+        let crc = crc32(&record);
+        record.extend_from_slice(&crc.to_le_bytes());
+        record
+    };
+    for corrupt in &[checksummed(7, b"3"), checksummed(INSERT, b"\"three\"")] {
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut log = encode(&Op::Insert(1u32)).unwrap();
+        log.extend_from_slice(corrupt);
+        log.extend_from_slice(&encode(&Op::Insert(2u32)).unwrap());
+        fs::write(dir.join(LOG), &log).unwrap();
+
+        let e = DurableBst::<u32, BoxBst<u32>>::open(&dir, config).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        // the records after the corrupt one are kept, not cut off as if torn
+        assert_eq!(fs::read(dir.join(LOG)).unwrap(), log);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn garbled_records_fail_open_unless_last() {
+    use super::boxy::BoxBst;
+    use std::env;
+
+    let dir = env::temp_dir().join(format!("bonzai-nbst-garbled-log-{}", ::std::process::id()));
+    let config = DurableConfig::default();
+    let records: Vec<Vec<u8>> = (1..4u32).map(|n| encode(&Op::Insert(n)).unwrap()).collect();
+    for &garbled in &[0, 2] {
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut records = records.clone();
+        records[garbled][RECORD_HEAD] ^= 1;
+        let log = records.concat();
+        fs::write(dir.join(LOG), &log).unwrap();
+
+        let opened = DurableBst::<u32, BoxBst<u32>>::open(&dir, config);
+        if garbled < 2 {
+            // whole records follow, so a crash can't have garbled it
+            assert_eq!(opened.unwrap_err().kind(), io::ErrorKind::InvalidData);
+            assert_eq!(fs::read(dir.join(LOG)).unwrap(), log);
+        } else {
+            // the last record may have been garbled by a crash, so it's dropped
+            assert!(opened.unwrap().tree().into_iter().eq(&[1, 2]));
+            assert_eq!(fs::read(dir.join(LOG)).unwrap(), records[..2].concat());
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_snapshots_keep_changes() {
+    use super::bonzai::BonzaiBst;
+    use std::env;
+
+    let dir = env::temp_dir().join(format!("bonzai-nbst-snapshot-{}", ::std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let config = DurableConfig { snapshot_every: 3, sync: true };
+    let words = ["pear", "apple", "fig", "kiwi"];
+    {
+        let mut tree: DurableBst<String, BonzaiBst<String>> = DurableBst::open(&dir, config).unwrap();
+        // a directory in the way of the snapshot's temporary file
+        fs::create_dir_all(dir.join(format!("{}.tmp", SNAPSHOT))).unwrap();
+        for &word in &words[..3] {
+            assert!(tree.insert(word.to_owned()).unwrap());
+        }
+        assert!(tree.failed_snapshot().is_some());
+        assert_eq!(tree.len(), 3);
+
+        fs::remove_dir(dir.join(format!("{}.tmp", SNAPSHOT))).unwrap();
+        assert!(tree.insert(words[3].to_string()).unwrap());
+        assert!(tree.failed_snapshot().is_none());
+        assert!(tree.remove(&"fig".to_owned()).unwrap());
+    }
+    let tree: DurableBst<String, BonzaiBst<String>> = DurableBst::open(&dir, config).unwrap();
+    assert_eq!(tree.tree().into_iter().collect::<Vec<_>>(), ["apple", "kiwi", "pear"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,451 @@
+//! A flat, read-only on-disk tree, which is searched in place through a memory map.
+//!
+//! The file is a 24 byte header followed by an array of fixed size nodes, all little endian:
+//!
+//! ```text
+//! header: magic "BNZT", version: u32, key width: u32, count: u32, root: u32, reserved: u32
+//! node:   key: [u8; width], left: u32, right: u32
+//! ```
+//!
+//! Children are indices into the node array, like the `ChildId`s of a bonzai arena, with
+//! `u32::MAX` for no child. The writer lays out a balanced tree in breadth first order, so
+//! the top levels, which every search passes through, share the first few pages.
+
+use super::build::{self, NotSorted};
+use super::validate::InvariantError;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::u32;
+
+use memmap::Mmap;
+
+pub const MAGIC: &[u8; 4] = b"BNZT";
+pub const VERSION: u32 = 1;
+const HEADER_LEN: usize = 24;
+const NONE: u32 = u32::MAX;
+
+/// A fixed width key, stored little endian.
+pub trait Key: Ord + Copy {
+    const WIDTH: usize;
+
+    /// The key's bits, of which the low `WIDTH` bytes are stored.
+    fn to_bits(self) -> u64;
+
+    fn from_bits(bits: u64) -> Self;
+}
+impl Key for u16 {
+    const WIDTH: usize = 2;
+
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as u16
+    }
+}
+impl Key for u32 {
+    const WIDTH: usize = 4;
+
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as u32
+    }
+}
+impl Key for u64 {
+    const WIDTH: usize = 8;
+
+    fn to_bits(self) -> u64 {
+        self
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits
+    }
+}
+impl Key for i32 {
+    const WIDTH: usize = 4;
+
+    fn to_bits(self) -> u64 {
+        self as u32 as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as u32 as i32
+    }
+}
+impl Key for i64 {
+    const WIDTH: usize = 8;
+
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as i64
+    }
+}
+
+fn put_le(out: &mut Vec<u8>, bits: u64, width: usize) {
+    for i in 0..width {
+        out.push((bits >> (8 * i)) as u8);
+    }
+}
+
+fn get_le(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |bits, &byte| bits << 8 | byte as u64)
+}
+
+fn node_len<K: Key>() -> usize {
+    K::WIDTH + 8
+}
+
+/// Write strictly increasing keys, such as those of any `Bst` in iteration order, as a
+/// balanced tree.
+pub fn write_sorted<K: Key, W: Write>(out: &mut W, keys: &[K]) -> io::Result<()> {
+    build::check_sorted(keys).map_err(|NotSorted { index }| io::Error::new(
+        io::ErrorKind::InvalidInput, format!("key {} is not greater than the key before it", index)))?;
+    if keys.len() >= NONE as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many keys"));
+    }
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + keys.len() * node_len::<K>());
+    bytes.extend_from_slice(MAGIC);
+    put_le(&mut bytes, VERSION as u64, 4);
+    put_le(&mut bytes, K::WIDTH as u64, 4);
+    put_le(&mut bytes, keys.len() as u64, 4);
+    put_le(&mut bytes, if keys.is_empty() { NONE } else { 0 } as u64, 4);
+    put_le(&mut bytes, 0, 4);
+
+    // each queued range of keys becomes a subtree, and nodes are numbered in the order they
+    // are queued, so a child's index is known as soon as it is queued
+    let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+    let mut queued = 0;
+    if !keys.is_empty() {
+        queue.push_back((0, keys.len()));
+        queued += 1;
+    }
+    while let Some((start, end)) = queue.pop_front() {
+        let mid = start + build::left_len(end - start);
+        let mut child = |start: usize, end: usize| if start < end {
+            queue.push_back((start, end));
+            queued += 1;
+            queued as u64 - 1
+        } else {
+            NONE as u64
+        };
+        let left = child(start, mid);
+        let right = child(mid + 1, end);
+        put_le(&mut bytes, keys[mid].to_bits(), K::WIDTH);
+        put_le(&mut bytes, left, 4);
+        put_le(&mut bytes, right, 4);
+    }
+    out.write_all(&bytes)
+}
+
+/// A tree of `K` searched directly in a memory mapped file.
+///
+/// A corrupt file can't cause undefined behaviour, since every access is bounds checked,
+/// but it can make searches give wrong answers or panic. `validate` checks the whole file.
+pub struct MappedBst<K: Key> {
+    map: Mmap,
+    len: usize,
+    root: u32,
+    _key: PhantomData<K>,
+}
+impl<K: Key> MappedBst<K> {
+    /// Map a file, checking only its header and length, so that opening is constant time.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let map = unsafe { Mmap::map(&file)? };
+        let invalid = |what: String| Err(io::Error::new(io::ErrorKind::InvalidData, what));
+        if map.len() < HEADER_LEN || &map[0..4] != MAGIC {
+            return invalid("not a mapped tree file".to_owned());
+        }
+        let field = |i: usize| get_le(&map[4 * i..4 * i + 4]);
+        if field(1) != VERSION as u64 {
+            return invalid(format!("unsupported version {}", field(1)));
+        }
+        if field(2) != K::WIDTH as u64 {
+            return invalid(format!("keys are {} bytes wide, expected {}", field(2), K::WIDTH));
+        }
+        let len = field(3) as usize;
+        let root = field(4) as u32;
+        if map.len() != HEADER_LEN + len * node_len::<K>() {
+            return invalid(format!("{} bytes long, expected {} for {} nodes",
+                                   map.len(), HEADER_LEN + len * node_len::<K>(), len));
+        }
+        // searches start from the root without checking it, so it must be a node, or the
+        // none sentinel if there are no nodes
+        let root_in_bounds = if len == 0 { root == NONE } else { (root as usize) < len };
+        if !root_in_bounds {
+            return invalid(format!("root {} is out of bounds of {} nodes", root, len));
+        }
+        Ok(MappedBst {
+            map,
+            len,
+            root,
+            _key: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn node(&self, index: u32) -> Node<K> {
+        let start = HEADER_LEN + index as usize * node_len::<K>();
+        let bytes = &self.map[start..start + node_len::<K>()];
+        let child = |i: usize| match get_le(&bytes[K::WIDTH + 4 * i..K::WIDTH + 4 * i + 4]) as u32 {
+            NONE => None,
+            index => Some(index),
+        };
+        Node {
+            key: K::from_bits(get_le(&bytes[..K::WIDTH])),
+            children: [child(0), child(1)],
+        }
+    }
+
+    fn root(&self) -> Option<u32> {
+        if self.root == NONE { None } else { Some(self.root) }
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        let mut curr = self.root();
+        // a well formed tree can't be deeper than it has nodes, but a corrupt one can loop
+        for _ in 0..self.len {
+            let node = match curr {
+                Some(index) => self.node(index),
+                None => return false,
+            };
+            curr = match key.cmp(&node.key) {
+                ::std::cmp::Ordering::Equal => return true,
+                ::std::cmp::Ordering::Less => node.children[0],
+                ::std::cmp::Ordering::Greater => node.children[1],
+            };
+        }
+        false
+    }
+
+    /// The keys in `range`, in order.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<K> {
+        let below_start = |key: &K| match range.start_bound() {
+            Bound::Included(start) => key < start,
+            Bound::Excluded(start) => key <= start,
+            Bound::Unbounded => false,
+        };
+        // seek the first key in range, stacking each node we go left from, which are
+        // exactly the nodes still to be visited
+        let mut stack = Vec::new();
+        let mut curr = self.root();
+        while let Some(index) = curr {
+            let node = self.node(index);
+            if below_start(&node.key) {
+                curr = node.children[1];
+            } else {
+                curr = node.children[0];
+                stack.push(node);
+            }
+            if stack.len() > self.len {
+                break;
+            }
+        }
+        Range {
+            tree: self,
+            stack,
+            end: match range.end_bound() {
+                Bound::Included(&end) => Bound::Included(end),
+                Bound::Excluded(&end) => Bound::Excluded(end),
+                Bound::Unbounded => Bound::Unbounded,
+            },
+            remaining: self.len,
+        }
+    }
+
+    pub fn iter(&self) -> Range<K> {
+        self.range(..)
+    }
+
+    /// Check every node of the file: that child indices are in bounds, that every node is
+    /// reached exactly once from the root, and that keys are in order.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        let mut seen = vec![false; self.len];
+        let mut prev: Option<K> = None;
+        let mut count = 0;
+        // in order, with the depth of each stacked node, whose path is the first `depth`
+        // branches of the path to the node last reached, as in `validate::check_by`
+        let mut path: Vec<usize> = Vec::new();
+        let mut stack: Vec<(Node<K>, usize)> = Vec::new();
+        let mut next = self.root().map(|root| (root, 0));
+        loop {
+            while let Some((index, depth)) = next.take() {
+                if index as usize >= self.len {
+                    return Err(InvariantError::new(path, format!(
+                        "node index {} is out of bounds of {} nodes", index, self.len)));
+                }
+                if seen[index as usize] {
+                    return Err(InvariantError::new(path, format!(
+                        "node {} is reached more than once", index)));
+                }
+                seen[index as usize] = true;
+                let node = self.node(index);
+                if let Some(left) = node.children[0] {
+                    path.push(0);
+                    next = Some((left, depth + 1));
+                }
+                stack.push((node, depth));
+            }
+            let (node, depth) = match stack.pop() {
+                Some(frame) => frame,
+                None => break,
+            };
+            path.truncate(depth);
+            if prev.map(|prev| prev >= node.key).unwrap_or(false) {
+                return Err(InvariantError::new(path, "key is out of order".to_owned()));
+            }
+            prev = Some(node.key);
+            count += 1;
+            if let Some(right) = node.children[1] {
+                path.push(1);
+                next = Some((right, depth + 1));
+            }
+        }
+        if count != self.len {
+            return Err(InvariantError::new(Vec::new(), format!(
+                "header says {} nodes, but {} are reachable", self.len, count)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Node<K> {
+    key: K,
+    children: [Option<u32>; 2],
+}
+
+pub struct Range<'t, K: Key + 't> {
+    tree: &'t MappedBst<K>,
+    stack: Vec<Node<K>>,
+    end: Bound<K>,
+    // guards against cycles in a corrupt file
+    remaining: usize,
+}
+impl<'t, K: Key> Iterator for Range<'t, K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        let node = self.stack.pop()?;
+        let past_end = match self.end {
+            Bound::Included(end) => node.key > end,
+            Bound::Excluded(end) => node.key >= end,
+            Bound::Unbounded => false,
+        };
+        if past_end || self.remaining == 0 {
+            self.stack.clear();
+            return None;
+        }
+        self.remaining -= 1;
+        // the next key is the leftmost of the right subtree, if there is one
+        let mut curr = node.children[1];
+        while let Some(index) = curr {
+            let child = self.tree.node(index);
+            curr = child.children[0];
+            self.stack.push(child);
+            if self.stack.len() > self.tree.len {
+                break;
+            }
+        }
+        Some(node.key)
+    }
+}
+
+#[test]
+fn mapped_round_trip() {
+    use std::collections::BTreeSet;
+    use std::env;
+    use std::fs;
+
+    let keys: Vec<i32> = (0..1000).map(|n| n * 7 - 3000).collect();
+    let path = env::temp_dir().join(format!("bonzai-nbst-mapped-{}.bin", ::std::process::id()));
+    write_sorted(&mut File::create(&path).unwrap(), &keys).unwrap();
+    let mapped: MappedBst<i32> = MappedBst::open(&path).unwrap();
+    let set: BTreeSet<i32> = keys.iter().cloned().collect();
+
+    mapped.validate().unwrap();
+    assert_eq!(mapped.len(), keys.len());
+    assert!(mapped.iter().eq(set.iter().cloned()));
+    for key in -3010..4010 {
+        assert_eq!(mapped.contains(&key), set.contains(&key), "contains({})", key);
+    }
+    assert!(mapped.range(-5..100).eq(set.range(-5..100).cloned()));
+    assert!(mapped.range(4..=4).eq(set.range(4..=4).cloned()));
+    assert!(MappedBst::<i64>::open(&path).is_err());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn open_rejects_corrupt_root() {
+    use std::env;
+    use std::fs;
+
+    let path = env::temp_dir().join(format!("bonzai-nbst-root-{}.bin", ::std::process::id()));
+    let open = |keys: &[i32], root: u32| {
+        let mut bytes = Vec::new();
+        write_sorted(&mut bytes, keys).unwrap();
+        bytes[16..20].copy_from_slice(&root.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        MappedBst::<i32>::open(&path).map(|tree| tree.iter().count())
+    };
+    assert_eq!(open(&[], NONE).unwrap(), 0);
+    assert_eq!(open(&[1, 2, 3], 2).unwrap(), 1);
+    for &(keys, root) in &[(&[][..], 0), (&[1, 2, 3][..], 3), (&[1, 2, 3][..], NONE)] {
+        let e = open(keys, root).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData, "root {} of {} nodes", root, keys.len());
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn validate_finds_corrupt_nodes() {
+    use std::env;
+    use std::fs;
+
+    // nodes are numbered breadth first, so node 4 is at root -> left -> right, and node 6
+    // at root -> right -> right
+    let mut bytes = Vec::new();
+    write_sorted(&mut bytes, &(0..7).collect::<Vec<i32>>()).unwrap();
+    let path = env::temp_dir().join(format!("bonzai-nbst-corrupt-{}.bin", ::std::process::id()));
+    let node = |index: usize| HEADER_LEN + index * node_len::<i32>();
+    let validate = |bytes: &[u8]| {
+        fs::write(&path, bytes).unwrap();
+        MappedBst::<i32>::open(&path).unwrap().validate()
+    };
+    assert!(validate(&bytes).is_ok());
+
+    let mut out_of_order = bytes.clone();
+    let key = node(4);
+    out_of_order[key..key + 4].copy_from_slice(&(-100i32).to_le_bytes());
+    let e = validate(&out_of_order).unwrap_err();
+    assert_eq!((e.path, e.reason), (vec![0, 1], "key is out of order".to_owned()));
+
+    // node 6's right child pointing back up at the root
+    let mut cycle = bytes;
+    let right = node(6) + 4 + 4;
+    cycle[right..right + 4].copy_from_slice(&0u32.to_le_bytes());
+    let e = validate(&cycle).unwrap_err();
+    assert_eq!((e.path, e.reason), (vec![1, 1, 1], "node 0 is reached more than once".to_owned()));
+    fs::remove_file(&path).unwrap();
+}
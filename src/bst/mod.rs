@@ -2,17 +2,89 @@
 pub mod bonzai;
 pub mod stdlib;
 pub mod boxy;
+pub mod shape;
+pub mod validate;
+pub mod dot;
+pub mod pretty;
+pub mod build;
+pub mod compare;
+pub mod persistent;
+pub mod snapshot;
+pub mod concurrent;
+pub mod coupling;
+#[cfg(feature = "serde")]
+pub mod serialize;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod mapped;
+#[cfg(feature = "serde")]
+pub mod durable;
 
 use std::iter::IntoIterator;
 use std::fmt::Debug;
 
+use self::shape::TreeStats;
+use self::validate::InvariantError;
+use self::dot::Annotation;
+use self::pretty::{PrettyConfig, PrettyDisplay};
+use self::build::NotSorted;
+use self::snapshot::Snapshot;
+
 pub trait Bst<T: Ord + Debug>: Debug
     where for<'s> &'s Self: IntoIterator<Item = &'s T> {
     fn new() -> Self;
 
+    /// Build a tree from strictly increasing elements. Implementations override this to
+    /// build a balanced tree in linear time, rather than inserting each element in turn.
+    fn from_sorted(elems: Vec<T>) -> Result<Self, NotSorted> where Self: Sized {
+        build::check_sorted(&elems)?;
+        let mut tree = Self::new();
+        for elem in elems {
+            tree.insert(elem);
+        }
+        Ok(tree)
+    }
+
     fn insert(&mut self, elem: T) -> bool;
 
     fn remove(&mut self, elem: &T) -> bool;
 
     fn contains(&self, elem: &T) -> bool;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Statistics about the shape of the tree, if the implementation exposes its shape.
+    fn stats(&self) -> Option<TreeStats> {
+        None
+    }
+
+    /// A graphviz digraph of the tree, if the implementation exposes its shape.
+    fn to_dot(&self, _annotation: Option<Annotation>) -> Option<String> {
+        None
+    }
+
+    /// The tree drawn sideways, if the implementation exposes its shape.
+    fn pretty(&self, _config: &PrettyConfig) -> Option<String> {
+        None
+    }
+
+    /// Formats the tree as `pretty` draws it, or as its debug form if the implementation
+    /// doesn't expose its shape.
+    fn display<'a>(&'a self, config: &'a PrettyConfig) -> PrettyDisplay<'a, T, Self> {
+        PrettyDisplay::new(self, config)
+    }
+
+    /// Check the tree's structural invariants, describing the first one found broken.
+    fn validate(&self) -> Result<(), InvariantError> {
+        Ok(())
+    }
+
+    /// A frozen copy of the tree, which later changes to the tree don't affect.
+    fn snapshot(&self) -> Snapshot<Self> where Self: Clone + Sized {
+        Snapshot::new(self.clone())
+    }
 }
\ No newline at end of file
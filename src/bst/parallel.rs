@@ -0,0 +1,224 @@
+//! Parallel bulk operations, with rayon.
+//!
+//! Parallel iteration splits the tree at subtree boundaries: a piece of the tree is a run of
+//! subtrees and single elements, in order, and a piece which is one subtree splits into its
+//! left subtree, its root element, and its right subtree.
+//!
+//! A bonzai tree is changed through an operation which borrows the whole tree, so its
+//! nodes can only be linked from one thread. Its parallel build lays the elements out in
+//! pre-order in parallel, so that all which is left is to link each node under the last.
+
+use super::shape::NodeRef;
+use super::build::{self, NotSorted};
+
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+use rayon;
+use rayon::prelude::*;
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+
+/// Below this many elements, a parallel build builds sequentially.
+pub const SEQUENTIAL_LEN: usize = 1 << 12;
+
+enum Part<'t, T: 't, N> {
+    Subtree(N),
+    Elem(&'t T),
+}
+
+pub struct ParIter<'t, T: 't, N> {
+    parts: Vec<Part<'t, T, N>>,
+}
+impl<'t, T: 't, N: NodeRef<'t, T>> ParIter<'t, T, N> {
+    pub fn new(root: Option<N>) -> Self {
+        ParIter {
+            parts: root.into_iter().map(Part::Subtree).collect(),
+        }
+    }
+}
+impl<'t, T: Sync + 't, N: NodeRef<'t, T> + Send> ParallelIterator for ParIter<'t, T, N> {
+    type Item = &'t T;
+
+    fn drive_unindexed<C: UnindexedConsumer<&'t T>>(self, consumer: C) -> C::Result {
+        bridge_unindexed(self, consumer)
+    }
+}
+impl<'t, T: Sync + 't, N: NodeRef<'t, T> + Send> UnindexedProducer for ParIter<'t, T, N> {
+    type Item = &'t T;
+
+    fn split(mut self) -> (Self, Option<Self>) {
+        if let [Part::Subtree(_)] = self.parts[..] {
+            if let Some(Part::Subtree(node)) = self.parts.pop() {
+                let (left, right) = (node.child(0), node.child(1));
+                self.parts.extend(left.map(Part::Subtree));
+                self.parts.push(Part::Elem(node.elem()));
+                self.parts.extend(right.map(Part::Subtree));
+            }
+        }
+        if self.parts.len() < 2 {
+            return (self, None);
+        }
+        let half = self.parts.len() / 2;
+        let right = self.parts.split_off(half);
+        (self, Some(ParIter {
+            parts: right,
+        }))
+    }
+
+    fn fold_with<F: Folder<&'t T>>(self, mut folder: F) -> F {
+        for part in self.parts {
+            folder = match part {
+                Part::Elem(elem) => folder.consume(elem),
+                Part::Subtree(node) => folder.consume_iter(InOrder::new(node)),
+            };
+            if folder.full() {
+                break;
+            }
+        }
+        folder
+    }
+}
+
+struct InOrder<'t, T: 't, N> {
+    stack: Vec<N>,
+    _elem: PhantomData<&'t T>,
+}
+impl<'t, T: 't, N: NodeRef<'t, T>> InOrder<'t, T, N> {
+    fn new(root: N) -> Self {
+        let mut iter = InOrder {
+            stack: Vec::new(),
+            _elem: PhantomData,
+        };
+        iter.push_leftmost(Some(root));
+        iter
+    }
+
+    fn push_leftmost(&mut self, mut node: Option<N>) {
+        while let Some(curr) = node {
+            node = curr.child(0);
+            self.stack.push(curr);
+        }
+    }
+}
+impl<'t, T: 't, N: NodeRef<'t, T>> Iterator for InOrder<'t, T, N> {
+    type Item = &'t T;
+
+    fn next(&mut self) -> Option<&'t T> {
+        let node = self.stack.pop()?;
+        self.push_leftmost(node.child(1));
+        Some(node.elem())
+    }
+}
+
+/// As `build::check_sorted`, in parallel.
+pub fn check_sorted<T: Ord + Sync>(elems: &[T]) -> Result<(), NotSorted> {
+    match elems.par_windows(2).position_first(|pair| pair[0] >= pair[1]) {
+        Some(i) => Err(NotSorted {
+            index: i + 1,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Wrap each element, so that a parallel build can take elements out of disjoint slices.
+pub fn into_options<T: Send>(elems: Vec<T>) -> Vec<Option<T>> {
+    elems.into_par_iter().map(Some).collect()
+}
+
+/// The pre-order sequence of the balanced tree of strictly increasing elements, with a
+/// `None` for each empty subtree, laying out the left and right subtrees of each node in
+/// parallel.
+pub fn balanced_preorder<T: Send>(elems: Vec<T>) -> Vec<Option<T>> {
+    let mut elems = into_options(elems);
+    let mut preorder: Vec<Option<T>> = (0..2 * elems.len() + 1).into_par_iter()
+        .map(|_| None)
+        .collect();
+    lay_out_preorder(&mut elems, &mut preorder);
+    preorder
+}
+
+// a subtree of `len` elements is `2 * len + 1` long in pre-order, counting its empty
+// subtrees, and the recursion is only as deep as the balanced tree
+fn lay_out_preorder<T: Send>(elems: &mut [Option<T>], preorder: &mut [Option<T>]) {
+    let len = elems.len();
+    if len == 0 {
+        return;
+    }
+    let (left, rest) = elems.split_at_mut(build::left_len(len));
+    let (elem, right) = rest.split_first_mut().unwrap();
+    let (root, below) = preorder.split_first_mut().unwrap();
+    *root = elem.take();
+    let (left_preorder, right_preorder) = below.split_at_mut(2 * left.len() + 1);
+    if len <= SEQUENTIAL_LEN {
+        lay_out_preorder(left, left_preorder);
+        lay_out_preorder(right, right_preorder);
+    } else {
+        rayon::join(|| lay_out_preorder(left, left_preorder),
+                    || lay_out_preorder(right, right_preorder));
+    }
+}
+
+/// Sort and deduplicate new elements, keeping the first of each run of equal ones, then
+/// merge them with existing elements, keeping an existing element over an equal new one.
+pub fn merge_new<T: Ord + Send>(existing: Vec<T>, new: Vec<T>) -> Vec<T> {
+    let mut new = new;
+    new.par_sort();
+    new.dedup();
+    let mut merged = Vec::with_capacity(existing.len() + new.len());
+    let mut existing = existing.into_iter().peekable();
+    let mut new = new.into_iter().peekable();
+    loop {
+        let next = match (existing.peek(), new.peek()) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return merged,
+        };
+        if next == Ordering::Equal {
+            new.next();
+        }
+        merged.push(if next == Ordering::Greater { new.next() } else { existing.next() }.unwrap());
+    }
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    use super::Bst;
+    use super::bonzai::BonzaiBst;
+    use super::boxy::BoxBst;
+    use std::collections::BTreeSet;
+
+    fn check<B>(par_from_sorted: fn(Vec<i32>) -> Result<B, NotSorted>)
+        where B: Bst<i32> + ParallelExtend<i32>,
+              for<'s> &'s B: IntoIterator<Item = &'s i32> + IntoParallelIterator<Item = &'s i32> {
+
+        let elems: Vec<i32> = (0..100_000).map(|n| n * 3).collect();
+        let tree = par_from_sorted(elems.clone()).unwrap();
+        let sequential = B::from_sorted(elems.clone()).unwrap();
+        tree.validate().unwrap();
+        assert_eq!(tree.stats(), sequential.stats());
+        let collected: Vec<i32> = tree.par_iter().cloned().collect();
+        assert_eq!(collected, elems);
+
+        let mut unsorted = elems.clone();
+        unsorted.swap(500, 501);
+        assert_eq!(par_from_sorted(unsorted.clone()).err(), B::from_sorted(unsorted).err());
+
+        let mut tree = tree;
+        let mut model: BTreeSet<i32> = elems.into_iter().collect();
+        let new: Vec<i32> = (0..50_000).rev().map(|n| n * 5).collect();
+        model.extend(new.iter().cloned());
+        tree.par_extend(new);
+        tree.validate().unwrap();
+        assert!(tree.into_iter().eq(model.iter()));
+        assert_eq!(tree.len(), model.len());
+
+        let mut empty = B::new();
+        empty.par_extend(model.iter().cloned());
+        let balanced = B::from_sorted(model.into_iter().collect()).unwrap();
+        assert_eq!(empty.stats(), balanced.stats());
+    }
+
+    check::<BonzaiBst<i32>>(BonzaiBst::par_from_sorted);
+    check::<BoxBst<i32>>(BoxBst::par_from_sorted);
+}
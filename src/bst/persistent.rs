@@ -0,0 +1,343 @@
+//! An immutable AVL tree, in which a change copies only the path from the root to the
+//! changed node, and shares every other node with the version before it.
+
+use super::Bst;
+use super::shape::{self, NodeRef, TreeStats};
+use super::validate::{self, InvariantError};
+use super::dot::{self, Annotation};
+use super::pretty::{self, PrettyConfig};
+use super::build::{self, NotSorted};
+
+use std::cmp::{self, Ordering};
+use std::iter::{FromIterator, IntoIterator, Iterator};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+type Link<T> = Option<Arc<PersistentNode<T>>>;
+
+/// Versions are cheap to clone, and share nodes across threads, so that readers can keep a
+/// consistent version while a writer moves on.
+#[derive(Debug, Clone)]
+pub struct PersistentBst<T: Ord + Debug + Clone> {
+    root: Link<T>,
+    len: usize,
+}
+impl<T: Ord + Debug + Clone> PersistentBst<T> {
+    /// A new version with `elem` inserted, or a clone of this one if it's already present.
+    pub fn inserted(&self, elem: T) -> Self {
+        match insert(&self.root, elem) {
+            Some(root) => PersistentBst {
+                root,
+                len: self.len + 1,
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// A new version with `elem` removed, or a clone of this one if it isn't present.
+    pub fn removed(&self, elem: &T) -> Self {
+        match remove(&self.root, elem) {
+            Some(root) => PersistentBst {
+                root,
+                len: self.len - 1,
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// Whether two versions are the same version, rather than merely equal.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (&Some(ref a), &Some(ref b)) => Arc::ptr_eq(a, b),
+            (&None, &None) => true,
+            _ => false,
+        }
+    }
+}
+impl<T: Ord + Debug + Clone> Bst<T> for PersistentBst<T> {
+    fn new() -> Self {
+        PersistentBst {
+            root: None,
+            len: 0,
+        }
+    }
+
+    fn from_sorted(elems: Vec<T>) -> Result<Self, NotSorted> {
+        build::check_sorted(&elems)?;
+        let len = elems.len();
+        Ok(PersistentBst {
+            root: balanced(len, &mut elems.into_iter()),
+            len,
+        })
+    }
+
+    /// Move this handle on to a new version. Clones of the old version are unaffected.
+    fn insert(&mut self, elem: T) -> bool {
+        match insert(&self.root, elem) {
+            Some(root) => {
+                self.root = root;
+                self.len += 1;
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn remove(&mut self, elem: &T) -> bool {
+        match remove(&self.root, elem) {
+            Some(root) => {
+                self.root = root;
+                self.len -= 1;
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn contains(&self, elem: &T) -> bool {
+        let mut curr = &self.root;
+        while let Some(ref node) = *curr {
+            curr = match elem.cmp(&node.elem) {
+                Ordering::Equal => return true,
+                Ordering::Less => &node.children[0],
+                Ordering::Greater => &node.children[1],
+            };
+        }
+        false
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn stats(&self) -> Option<TreeStats> {
+        Some(shape::stats(self.root.as_ref().map(|root| &**root)))
+    }
+
+    fn to_dot(&self, annotation: Option<Annotation>) -> Option<String> {
+        Some(dot::to_dot(self.root.as_ref().map(|root| &**root), annotation))
+    }
+
+    fn pretty(&self, config: &PrettyConfig) -> Option<String> {
+        Some(pretty::pretty(self.root.as_ref().map(|root| &**root), config))
+    }
+
+    fn validate(&self) -> Result<(), InvariantError> {
+        validate::check(self.root.as_ref().map(|root| &**root), self.len)?;
+        check_balance(&self.root, &mut Vec::new()).map(|_| ())
+    }
+}
+impl<'s, T: Ord + Debug + Clone> IntoIterator for &'s PersistentBst<T> {
+    type Item = &'s T;
+    type IntoIter = Iter<'s, T>;
+
+    fn into_iter(self) -> Iter<'s, T> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+        };
+        iter.push_leftmost(&self.root);
+        iter
+    }
+}
+impl<T: Ord + Debug + Clone> FromIterator<T> for PersistentBst<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let elems: Vec<T> = iter.into_iter().collect();
+        if build::check_sorted(&elems).is_ok() {
+            return Self::from_sorted(elems).unwrap();
+        }
+        let mut tree = Self::new();
+        for elem in elems {
+            tree.insert(elem);
+        }
+        tree
+    }
+}
+
+#[derive(Debug)]
+struct PersistentNode<T> {
+    elem: T,
+    height: u8,
+    children: [Link<T>; 2],
+}
+
+fn height<T>(link: &Link<T>) -> u8 {
+    link.as_ref().map(|node| node.height).unwrap_or(0)
+}
+
+fn node<T>(elem: T, left: Link<T>, right: Link<T>) -> Link<T> {
+    Some(Arc::new(PersistentNode {
+        elem,
+        height: cmp::max(height(&left), height(&right)) + 1,
+        children: [left, right],
+    }))
+}
+
+// a node whose subtrees may differ in height by up to two, rotated back into balance
+fn balanced_node<T: Clone>(elem: T, left: Link<T>, right: Link<T>) -> Link<T> {
+    let (left_height, right_height) = (height(&left), height(&right));
+    if left_height > right_height + 1 {
+        let left = left.unwrap();
+        let (outer, inner) = (&left.children[0], &left.children[1]);
+        if height(outer) >= height(inner) {
+            node(left.elem.clone(), outer.clone(), node(elem, inner.clone(), right))
+        } else {
+            let inner = inner.as_ref().unwrap();
+            node(inner.elem.clone(),
+                 node(left.elem.clone(), outer.clone(), inner.children[0].clone()),
+                 node(elem, inner.children[1].clone(), right))
+        }
+    } else if right_height > left_height + 1 {
+        let right = right.unwrap();
+        let (inner, outer) = (&right.children[0], &right.children[1]);
+        if height(outer) >= height(inner) {
+            node(right.elem.clone(), node(elem, left, inner.clone()), outer.clone())
+        } else {
+            let inner = inner.as_ref().unwrap();
+            node(inner.elem.clone(),
+                 node(elem, left, inner.children[0].clone()),
+                 node(right.elem.clone(), inner.children[1].clone(), outer.clone()))
+        }
+    } else {
+        node(elem, left, right)
+    }
+}
+
+// the recursion is only as deep as the balanced tree, as are insert and remove
+fn balanced<T, I: Iterator<Item = T>>(len: usize, elems: &mut I) -> Link<T> {
+    if len == 0 {
+        return None;
+    }
+    let left_len = build::left_len(len);
+    let left = balanced(left_len, elems);
+    let elem = elems.next().unwrap();
+    let right = balanced(len - 1 - left_len, elems);
+    node(elem, left, right)
+}
+
+/// The new subtree, or none if the element was already present and nothing changed.
+fn insert<T: Ord + Clone>(link: &Link<T>, elem: T) -> Option<Link<T>> {
+    let node = match *link {
+        Some(ref node) => node,
+        None => return Some(self::node(elem, None, None)),
+    };
+    match elem.cmp(&node.elem) {
+        Ordering::Equal => None,
+        Ordering::Less => insert(&node.children[0], elem).map(|left|
+            balanced_node(node.elem.clone(), left, node.children[1].clone())),
+        Ordering::Greater => insert(&node.children[1], elem).map(|right|
+            balanced_node(node.elem.clone(), node.children[0].clone(), right)),
+    }
+}
+
+/// The new subtree, or none if the element wasn't present and nothing changed.
+fn remove<T: Ord + Clone>(link: &Link<T>, elem: &T) -> Option<Link<T>> {
+    let node = link.as_ref()?;
+    match elem.cmp(&node.elem) {
+        Ordering::Less => remove(&node.children[0], elem).map(|left|
+            balanced_node(node.elem.clone(), left, node.children[1].clone())),
+        Ordering::Greater => remove(&node.children[1], elem).map(|right|
+            balanced_node(node.elem.clone(), node.children[0].clone(), right)),
+        Ordering::Equal => Some(match (&node.children[0], &node.children[1]) {
+            (left, &None) => left.clone(),
+            (&None, right) => right.clone(),
+            (left, &Some(ref right)) => {
+                // replace this node's element with the least element of the right subtree
+                let (least, right) = remove_least(right);
+                balanced_node(least, left.clone(), right)
+            },
+        }),
+    }
+}
+
+fn remove_least<T: Clone>(node: &Arc<PersistentNode<T>>) -> (T, Link<T>) {
+    match node.children[0] {
+        Some(ref left) => {
+            let (least, left) = remove_least(left);
+            (least, balanced_node(node.elem.clone(), left, node.children[1].clone()))
+        },
+        None => (node.elem.clone(), node.children[1].clone()),
+    }
+}
+
+// the height of the subtree, if every stored height is right and every node is balanced
+fn check_balance<T>(link: &Link<T>, path: &mut Vec<usize>) -> Result<u8, InvariantError> {
+    let node = match *link {
+        Some(ref node) => node,
+        None => return Ok(0),
+    };
+    let mut heights = [0; 2];
+    for (branch, height) in heights.iter_mut().enumerate() {
+        path.push(branch);
+        *height = check_balance(&node.children[branch], path)?;
+        path.pop();
+    }
+    if (heights[0] as i16 - heights[1] as i16).abs() > 1 {
+        return Err(InvariantError::new(path.clone(), format!(
+            "subtrees differ in height by more than one: {} and {}", heights[0], heights[1])));
+    }
+    let height = cmp::max(heights[0], heights[1]) + 1;
+    if node.height != height {
+        return Err(InvariantError::new(path.clone(), format!(
+            "stored height is {}, but the subtree's height is {}", node.height, height)));
+    }
+    Ok(height)
+}
+
+impl<'t, T: Ord + Debug> NodeRef<'t, T> for &'t PersistentNode<T> {
+    fn elem(&self) -> &'t T {
+        &self.elem
+    }
+
+    fn child(&self, branch: usize) -> Option<Self> {
+        self.children[branch].as_ref().map(|child| &**child)
+    }
+}
+
+pub struct Iter<'t, T: 't> {
+    stack: Vec<&'t PersistentNode<T>>,
+}
+impl<'t, T> Iter<'t, T> {
+    fn push_leftmost(&mut self, mut link: &'t Link<T>) {
+        while let Some(ref node) = *link {
+            self.stack.push(node);
+            link = &node.children[0];
+        }
+    }
+}
+impl<'t, T> Iterator for Iter<'t, T> {
+    type Item = &'t T;
+
+    fn next(&mut self) -> Option<&'t T> {
+        let curr = self.stack.pop()?;
+        self.push_leftmost(&curr.children[1]);
+        Some(&curr.elem)
+    }
+}
+
+#[test]
+fn versions_are_independent() {
+    let empty: PersistentBst<i32> = PersistentBst::new();
+    let mut versions = vec![empty];
+    for n in 0..200 {
+        let next = if n % 4 == 3 {
+            versions[n as usize].removed(&(n * 7 % 50))
+        } else {
+            versions[n as usize].inserted(n * 7 % 50)
+        };
+        versions.push(next);
+    }
+    let mut model = ::std::collections::BTreeSet::new();
+    for (n, version) in versions.iter().enumerate() {
+        if let Err(e) = version.validate() {
+            panic!("version {}: {}", n, e);
+        }
+        assert!(version.into_iter().eq(model.iter()), "version {}", n);
+        let n = n as i32;
+        if n % 4 == 3 {
+            model.remove(&(n * 7 % 50));
+        } else {
+            model.insert(n * 7 % 50);
+        }
+    }
+    assert!(versions[3].inserted(0).ptr_eq(&versions[3]));
+}
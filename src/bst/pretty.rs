@@ -0,0 +1,148 @@
+use super::Bst;
+use super::shape::NodeRef;
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::marker::PhantomData;
+
+/// Limits on how much of a tree to draw, so that a dump of a large tree stays readable.
+#[derive(Debug, Copy, Clone)]
+pub struct PrettyConfig {
+    /// Nodes deeper than this, with the root at depth 0, are elided.
+    pub max_depth: usize,
+    /// Lines longer than this many characters are cut off.
+    pub max_width: usize,
+    /// Lines past this many are elided, leaving a count of how many were.
+    pub max_lines: usize,
+}
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig {
+            max_depth: 12,
+            max_width: 120,
+            max_lines: 200,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Edge {
+    Root,
+    Left,
+    Right,
+}
+
+/// Draw the tree below `root` sideways, with the root at the left and right subtrees above
+/// their parents, so that it reads in order from the bottom up:
+///
+/// ```text
+///     ┌── 7
+/// ┌── 6
+/// 4
+/// │   ┌── 3
+/// └── 2
+///     └── 1
+/// ```
+pub fn pretty<'t, T: Debug + 't, N: NodeRef<'t, T>>(root: Option<N>, config: &PrettyConfig)
+    -> String {
+
+    let mut lines = Vec::new();
+    match root {
+        Some(root) => draw(&root, 0, String::new(), Edge::Root, config, &mut lines),
+        None => lines.push("(empty)".to_owned()),
+    };
+    if lines.len() > config.max_lines {
+        let elided = lines.len() - config.max_lines;
+        lines.truncate(config.max_lines);
+        lines.push(format!("… {} more lines", elided));
+    }
+    let lines: Vec<String> = lines.into_iter()
+        .map(|line| if line.chars().count() > config.max_width {
+            let mut cut: String = line.chars().take(config.max_width.saturating_sub(1)).collect();
+            cut.push('…');
+            cut
+        } else {
+            line
+        })
+        .collect();
+    lines.join("\n")
+}
+
+// recursion is bounded by the max depth
+fn draw<'t, T: Debug + 't, N: NodeRef<'t, T>>(node: &N, depth: usize, prefix: String, edge: Edge,
+                                              config: &PrettyConfig, lines: &mut Vec<String>) {
+    let (connector, above, below) = match edge {
+        Edge::Root => ("", "", ""),
+        // the line from a right child down to its parent passes its left subtree
+        Edge::Right => ("┌── ", "    ", "│   "),
+        // and the line from a left child up to its parent passes its right subtree
+        Edge::Left => ("└── ", "│   ", "    "),
+    };
+    let (left, right) = (node.child(0), node.child(1));
+    let elided = depth == config.max_depth && (left.is_some() || right.is_some());
+
+    if !elided {
+        if let Some(ref right) = right {
+            draw(right, depth + 1, format!("{}{}", prefix, above), Edge::Right, config, lines);
+        }
+    }
+    lines.push(format!("{}{}{:?}{}", prefix, connector, node.elem(),
+                       if elided { " …" } else { "" }));
+    if !elided {
+        if let Some(ref left) = left {
+            draw(left, depth + 1, format!("{}{}", prefix, below), Edge::Left, config, lines);
+        }
+    }
+}
+
+/// Formats a tree as `Bst::pretty` draws it, or as its debug form if it doesn't expose its
+/// shape. Made by `Bst::display`.
+pub struct PrettyDisplay<'a, T, B: ?Sized + 'a> {
+    tree: &'a B,
+    config: &'a PrettyConfig,
+    _elem: PhantomData<fn() -> T>,
+}
+impl<'a, T, B: ?Sized> PrettyDisplay<'a, T, B> {
+    pub fn new(tree: &'a B, config: &'a PrettyConfig) -> Self {
+        PrettyDisplay {
+            tree,
+            config,
+            _elem: PhantomData,
+        }
+    }
+}
+impl<'a, T: Ord + Debug, B: Bst<T> + ?Sized> Display for PrettyDisplay<'a, T, B>
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.tree.pretty(self.config) {
+            Some(drawing) => f.write_str(&drawing),
+            None => write!(f, "{:#?}", self.tree),
+        }
+    }
+}
+
+#[test]
+fn pretty_limits_lines() {
+    use super::boxy::BoxBst;
+    use super::stdlib::BTreeSet;
+
+    let tree = BoxBst::from_sorted((1..8).collect()).unwrap();
+    let mut config = PrettyConfig::default();
+    assert_eq!(tree.display(&config).to_string(), [
+        "    ┌── 7",
+        "┌── 6",
+        "│   └── 5",
+        "4",
+        "│   ┌── 3",
+        "└── 2",
+        "    └── 1",
+    ].join("\n"));
+
+    config.max_lines = 3;
+    assert_eq!(tree.display(&config).to_string(), "    ┌── 7\n┌── 6\n│   └── 5\n… 4 more lines");
+    config.max_depth = 0;
+    assert_eq!(tree.display(&config).to_string(), "4 …");
+
+    let set: BTreeSet<i32> = Bst::from_sorted(vec![1, 2]).unwrap();
+    assert_eq!(set.display(&config).to_string(), format!("{:#?}", set));
+}
@@ -0,0 +1,224 @@
+//! Serde support, behind the `serde` feature.
+//!
+//! Trees serialize as the sequence of their elements in sorted order, which deserializes
+//! through the balanced bulk build, so the format doesn't depend on the backend or on the
+//! order the elements were inserted in. To round trip the exact node structure instead,
+//! use `#[serde(with = "bst::serialize::shaped")]`.
+
+use super::bonzai::BonzaiBstBy;
+use super::boxy::BoxBstBy;
+use super::shape::NodeRef;
+use super::compare::Compare;
+
+use std::fmt::Debug;
+
+use serde::ser::{Serialize, Serializer};
+use serde::de::{Deserialize, Deserializer, Error};
+
+/// A tree which can be taken apart into, and rebuilt from, its pre-order sequence of
+/// elements, in which each empty child slot is `None`.
+pub trait Shape<T: Ord>: Sized {
+    fn preorder(&self) -> Vec<Option<&T>>;
+
+    /// Build the tree from a pre-order sequence which `check_preorder` has accepted.
+    fn from_checked_preorder(tokens: Vec<Option<T>>) -> Self;
+
+    /// Check the pre-order sequence, so that every backend rejects malformed input the same
+    /// way, then build the tree from it.
+    fn from_preorder(tokens: Vec<Option<T>>) -> Result<Self, String> {
+        check_preorder(&tokens)?;
+        Ok(Self::from_checked_preorder(tokens))
+    }
+}
+
+/// The pre-order sequence of the tree below `root`.
+pub fn preorder<'t, T: 't, N: NodeRef<'t, T>>(root: Option<N>) -> Vec<Option<&'t T>> {
+    let mut tokens = Vec::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        match node {
+            Some(node) => {
+                tokens.push(Some(node.elem()));
+                stack.push(node.child(1));
+                stack.push(node.child(0));
+            },
+            None => tokens.push(None),
+        };
+    }
+    tokens
+}
+
+/// Rebuild a tree from its pre-order sequence, with `node` joining an element to its left
+/// and right subtrees. This works back from the end of the sequence, where every subtree
+/// is complete before its parent is reached, so deep trees can't overflow the stack.
+pub fn rebuild<T, N, F>(tokens: Vec<Option<T>>, mut node: F) -> Result<Option<N>, String>
+    where F: FnMut(T, Option<N>, Option<N>) -> N {
+
+    let malformed = || "malformed pre-order sequence".to_owned();
+    let mut subtrees: Vec<Option<N>> = Vec::new();
+    for token in tokens.into_iter().rev() {
+        match token {
+            Some(elem) => {
+                let left = subtrees.pop().ok_or_else(malformed)?;
+                let right = subtrees.pop().ok_or_else(malformed)?;
+                subtrees.push(Some(node(elem, left, right)));
+            },
+            None => subtrees.push(None),
+        };
+    }
+    match subtrees.len() {
+        1 => Ok(subtrees.pop().unwrap()),
+        _ => Err(malformed()),
+    }
+}
+
+/// Check that a pre-order sequence describes a well formed search tree, without building
+/// it: each element must fall strictly between the bounds its position implies.
+pub fn check_preorder<T: Ord>(tokens: &[Option<T>]) -> Result<(), String> {
+    // the bounds of the positions still to be filled, the next one on top
+    let mut positions: Vec<(Option<&T>, Option<&T>)> = vec![(None, None)];
+    for (i, token) in tokens.iter().enumerate() {
+        let (low, high) = positions.pop().ok_or_else(|| "malformed pre-order sequence".to_owned())?;
+        if let Some(ref elem) = *token {
+            if low.map(|low| low >= elem).unwrap_or(false) || high.map(|high| high <= elem).unwrap_or(false) {
+                return Err(format!("element {} is out of order", i));
+            }
+            positions.push((Some(elem), high));
+            positions.push((low, Some(elem)));
+        }
+    }
+    match positions.len() {
+        0 => Ok(()),
+        _ => Err("malformed pre-order sequence".to_owned()),
+    }
+}
+
+pub mod shaped {
+    use super::Shape;
+
+    use serde::ser::{Serialize, Serializer};
+    use serde::de::{Deserialize, Deserializer, Error};
+
+    pub fn serialize<T: Ord + Serialize, B: Shape<T>, S: Serializer>(tree: &B, serializer: S)
+        -> Result<S::Ok, S::Error> {
+
+        serializer.collect_seq(tree.preorder())
+    }
+
+    pub fn deserialize<'de, T, B, D>(deserializer: D) -> Result<B, D::Error>
+        where T: Ord + Deserialize<'de>,
+              B: Shape<T>,
+              D: Deserializer<'de> {
+
+        let tokens: Vec<Option<T>> = Vec::deserialize(deserializer)?;
+        B::from_preorder(tokens).map_err(D::Error::custom)
+    }
+}
+
+impl<T: Debug + Serialize, C: Compare<T>> Serialize for BonzaiBstBy<T, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+impl<'de, T: Debug + Deserialize<'de>, C: Compare<T> + Default> Deserialize<'de> for BonzaiBstBy<T, C> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let elems: Vec<T> = Vec::deserialize(deserializer)?;
+        BonzaiBstBy::from_sorted_by(elems, C::default()).map_err(D::Error::custom)
+    }
+}
+
+impl<T: Debug + Serialize, C: Compare<T>> Serialize for BoxBstBy<T, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+impl<'de, T: Debug + Deserialize<'de>, C: Compare<T> + Default> Deserialize<'de> for BoxBstBy<T, C> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let elems: Vec<T> = Vec::deserialize(deserializer)?;
+        BoxBstBy::from_sorted_by(elems, C::default()).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+fn round_trips<B>()
+    where B: super::Bst<i32> + Shape<i32> + Serialize + for<'de> Deserialize<'de>,
+          for<'s> &'s B: IntoIterator<Item = &'s i32> {
+    // an irregular shape, which the sorted form doesn't keep but the shaped form does
+    let mut tree = B::new();
+    for i in 0..200 {
+        tree.insert((i * 37) % 200 - 100);
+    }
+    let elems: Vec<i32> = (&tree).into_iter().cloned().collect();
+    let balanced = B::from_sorted(elems.clone()).unwrap();
+
+    let sorted: B = serde_json::from_str(&serde_json::to_string(&tree).unwrap()).unwrap();
+    assert_eq!((&sorted).into_iter().cloned().collect::<Vec<_>>(), elems);
+    assert_eq!(sorted.stats(), balanced.stats());
+    assert!(sorted.validate().is_ok());
+
+    let mut json = Vec::new();
+    shaped::serialize(&tree, &mut serde_json::Serializer::new(&mut json)).unwrap();
+    let shaped: B = shaped::deserialize(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+    assert_eq!(shaped.stats(), tree.stats());
+    assert_eq!(shaped.len(), tree.len());
+    assert!(shaped.validate().is_ok());
+
+    for json in &["[1, 3, 2]", "[1, 1]"] {
+        assert!(serde_json::from_str::<B>(json).is_err(), "accepted {}", json);
+    }
+    let from_shaped = |json: &str| -> Result<B, serde_json::Error> {
+        shaped::deserialize(&mut serde_json::Deserializer::from_str(json))
+    };
+    for json in &["[2, 3, null, null, null]", "[2, null, 1, null, null]", "[2, null]",
+                  "[2, null, null, null]", "[]"] {
+        assert!(from_shaped(json).is_err(), "accepted {}", json);
+    }
+    assert_eq!(from_shaped("[null]").unwrap().len(), 0);
+}
+
+#[test]
+fn bonzai_round_trips() {
+    use super::bonzai::BonzaiBst;
+
+    round_trips::<BonzaiBst<i32>>();
+}
+
+#[test]
+fn boxy_round_trips() {
+    use super::boxy::BoxBst;
+
+    round_trips::<BoxBst<i32>>();
+}
+
+#[test]
+fn rejects_malformed_preorder() {
+    let well_formed = vec![Some(5), Some(2), None, Some(3), None, None, Some(8), None, None];
+    assert!(check_preorder(&well_formed).is_ok());
+    assert!(rebuild(well_formed, |elem, _, _| elem).is_ok());
+
+    let out_of_order = vec![Some(5), Some(2), None, Some(6), None, None, None];
+    assert_eq!(check_preorder(&out_of_order), Err("element 3 is out of order".to_owned()));
+    assert_eq!(check_preorder(&[Some(5), Some(5), None, None, None]),
+               Err("element 1 is out of order".to_owned()));
+    for malformed in &[vec![], vec![Some(1), None], vec![None, None], vec![Some(1), None, None, None]] {
+        assert_eq!(check_preorder(malformed), Err("malformed pre-order sequence".to_owned()));
+        assert_eq!(rebuild(malformed.clone(), |elem, _, _| elem),
+                   Err("malformed pre-order sequence".to_owned()));
+    }
+}
+
+#[test]
+fn backends_reject_the_same_preorder() {
+    use super::bonzai::BonzaiBst;
+    use super::boxy::BoxBst;
+
+    let errors = |json: &str| {
+        let bonzai: Result<BonzaiBst<i32>, _> = shaped::deserialize(&mut serde_json::Deserializer::from_str(json));
+        let boxy: Result<BoxBst<i32>, _> = shaped::deserialize(&mut serde_json::Deserializer::from_str(json));
+        (bonzai.unwrap_err().to_string(), boxy.unwrap_err().to_string())
+    };
+    for json in &["[2, 3, null, null, null]", "[2, null, 2, null, null]", "[2, null]"] {
+        let (bonzai, boxy) = errors(json);
+        assert_eq!(bonzai, boxy, "{}", json);
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt::{self, Display, Formatter};
+
+/// A read-only handle to a node of a binary tree, which is enough to walk the tree's shape
+/// without knowing how its nodes are stored.
+pub trait NodeRef<'t, T: 't>: Sized {
+    fn elem(&self) -> &'t T;
+
+    /// The child at branch 0 (left) or 1 (right), if present.
+    fn child(&self, branch: usize) -> Option<Self>;
+
+    /// Check that `child`, found at `branch` of this node, links back up to it. Only trees
+    /// which store parent links have anything to check.
+    fn check_link(&self, _branch: usize, _child: &Self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    pub nodes: usize,
+    /// The number of nodes on the longest path from the root, so 0 for an empty tree.
+    pub height: usize,
+    /// The mean distance of a node from the root, which is at depth 0.
+    pub avg_depth: f64,
+    pub leaves: usize,
+    pub one_child: usize,
+    pub two_children: usize,
+    /// The largest difference between the heights of a node's two subtrees.
+    pub worst_imbalance: usize,
+    /// The number of nodes at each depth.
+    pub depths: Vec<usize>,
+}
+impl Display for TreeStats {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "    shape    {} nodes, height {}, avg depth {:.2}, worst imbalance {}",
+                 self.nodes, self.height, self.avg_depth, self.worst_imbalance)?;
+        writeln!(f, "             {} leaves, {} with one child, {} with two children",
+                 self.leaves, self.one_child, self.two_children)?;
+        let depths: Vec<String> = self.depths.iter().map(|n| n.to_string()).collect();
+        write!(f, "             nodes per depth: {}", depths.join(" "))
+    }
+}
+
+/// Walk the tree below `root`, without recursion, so that degenerate trees can't overflow
+/// the stack.
+pub fn stats<'t, T: 't, N: NodeRef<'t, T>>(root: Option<N>) -> TreeStats {
+    let mut stats = TreeStats {
+        nodes: 0,
+        height: 0,
+        avg_depth: 0.0,
+        leaves: 0,
+        one_child: 0,
+        two_children: 0,
+        worst_imbalance: 0,
+        depths: Vec::new(),
+    };
+    let root = match root {
+        Some(root) => root,
+        None => return stats,
+    };
+
+    let mut depth_sum = 0;
+    // post-order: a node is visited once to push its children, then again once both of
+    // their subtree heights are on the height stack
+    let mut stack: Vec<(N, usize, bool)> = vec![(root, 0, false)];
+    let mut heights: Vec<usize> = Vec::new();
+    while let Some((node, depth, expanded)) = stack.pop() {
+        let (left, right) = (node.child(0), node.child(1));
+        if !expanded {
+            stats.nodes += 1;
+            depth_sum += depth;
+            if stats.depths.len() <= depth {
+                stats.depths.push(0);
+            }
+            stats.depths[depth] += 1;
+            match (left.is_some(), right.is_some()) {
+                (false, false) => stats.leaves += 1,
+                (true, true) => stats.two_children += 1,
+                _ => stats.one_child += 1,
+            };
+
+            stack.push((node, depth, true));
+            if let Some(right) = right {
+                stack.push((right, depth + 1, false));
+            }
+            if let Some(left) = left {
+                stack.push((left, depth + 1, false));
+            }
+        } else {
+            // the left subtree was finished first, so its height is below the right's
+            let right_height = if right.is_some() { heights.pop().unwrap() } else { 0 };
+            let left_height = if left.is_some() { heights.pop().unwrap() } else { 0 };
+            let imbalance = if left_height > right_height {
+                left_height - right_height
+            } else {
+                right_height - left_height
+            };
+            stats.worst_imbalance = stats.worst_imbalance.max(imbalance);
+            heights.push(1 + left_height.max(right_height));
+        }
+    }
+
+    stats.height = heights.pop().unwrap();
+    stats.avg_depth = depth_sum as f64 / stats.nodes as f64;
+    stats
+}
+
+#[test]
+fn stats_describe_known_shape() {
+    use super::Bst;
+    use super::bonzai::BonzaiBst;
+    use super::boxy::BoxBst;
+
+    //         4
+    //      2     6
+    //     1 3      7
+    //                8
+    let order = [4, 2, 6, 1, 3, 7, 8];
+    let expected = TreeStats {
+        nodes: 7,
+        height: 4,
+        avg_depth: (0 + 1 + 1 + 2 + 2 + 2 + 3) as f64 / 7.0,
+        leaves: 3,
+        one_child: 2,
+        two_children: 2,
+        // 6 has nothing on its left, and 7 and 8 on its right
+        worst_imbalance: 2,
+        depths: vec![1, 2, 3, 1],
+    };
+    let mut boxy: BoxBst<i32> = BoxBst::new();
+    let mut bonzai: BonzaiBst<i32> = BonzaiBst::new();
+    for &elem in &order {
+        boxy.insert(elem);
+        bonzai.insert(elem);
+    }
+    assert_eq!(boxy.stats(), Some(expected.clone()));
+    assert_eq!(bonzai.stats(), Some(expected));
+
+    let empty = BoxBst::<i32>::new().stats().unwrap();
+    assert_eq!((empty.nodes, empty.height, empty.depths.len()), (0, 0, 0));
+}
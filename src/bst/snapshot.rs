@@ -0,0 +1,31 @@
+//! Frozen, shareable views of a tree.
+
+use std::iter::IntoIterator;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A read-only copy of a tree, as it was when the snapshot was taken. Cloning the snapshot
+/// shares the copy, and it derefs to the tree, so only `&self` methods are reachable.
+#[derive(Debug)]
+pub struct Snapshot<B>(Arc<B>);
+impl<B> Snapshot<B> {
+    pub fn new(tree: B) -> Self {
+        Snapshot(Arc::new(tree))
+    }
+
+    pub fn iter<'s>(&'s self) -> <&'s B as IntoIterator>::IntoIter where &'s B: IntoIterator {
+        (&*self.0).into_iter()
+    }
+}
+impl<B> Clone for Snapshot<B> {
+    fn clone(&self) -> Self {
+        Snapshot(self.0.clone())
+    }
+}
+impl<B> Deref for Snapshot<B> {
+    type Target = B;
+
+    fn deref(&self) -> &B {
+        &self.0
+    }
+}
@@ -20,4 +20,8 @@ impl<T: Ord + Debug> Bst<T> for BTreeSet<T> {
     fn contains(&self, elem: &T) -> bool {
         self.contains(elem)
     }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,163 @@
+use super::shape::NodeRef;
+use super::compare::{Compare, Natural};
+
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// A broken structural invariant, and the path from the root to the node where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantError {
+    /// The branches taken from the root, 0 for left and 1 for right.
+    pub path: Vec<usize>,
+    pub reason: String,
+}
+impl InvariantError {
+    pub fn new(path: Vec<usize>, reason: String) -> Self {
+        InvariantError {
+            path,
+            reason,
+        }
+    }
+}
+impl Display for InvariantError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "at root")?;
+        for &branch in &self.path {
+            write!(f, " -> {}", if branch == 0 { "left" } else { "right" })?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+/// Walk the tree below `root` in order, checking that every element is strictly greater
+/// than the one before it, which rules out both misplaced elements and duplicates, that
+/// every child links back to its parent, and that the tree holds `len` elements.
+pub fn check<'t, T: Ord + Debug + 't, N: NodeRef<'t, T>>(root: Option<N>, len: usize)
+    -> Result<(), InvariantError> {
+    check_by(root, len, &Natural)
+}
+
+/// As `check`, but in the comparator's ordering.
+pub fn check_by<'t, T, N, C>(root: Option<N>, len: usize, cmp: &C) -> Result<(), InvariantError>
+    where T: Debug + 't, N: NodeRef<'t, T>, C: Compare<T> {
+
+    let mut count = 0;
+    let mut prev: Option<&'t T> = None;
+    // the path from the root to the node last reached. every node reached after a node is
+    // stacked, and before it's popped, is in its left subtree, so the path's first `depth`
+    // branches are still the stacked node's path when it's popped
+    let mut path: Vec<usize> = Vec::new();
+    // each node waiting to be visited, along with its depth
+    let mut stack: Vec<(N, usize)> = Vec::new();
+    let mut next = root.map(|root| (root, 0));
+    loop {
+        // descend to the leftmost node not yet visited
+        while let Some((node, depth)) = next.take() {
+            if let Some(left) = node.child(0) {
+                node.check_link(0, &left)
+                    .map_err(|reason| InvariantError::new(path.clone(), reason))?;
+                path.push(0);
+                next = Some((left, depth + 1));
+            }
+            stack.push((node, depth));
+        }
+        let (node, depth) = match stack.pop() {
+            Some(frame) => frame,
+            None => break,
+        };
+        path.truncate(depth);
+
+        let elem = node.elem();
+        if let Some(prev) = prev {
+            match cmp.compare(elem, prev) {
+                Ordering::Equal => return Err(InvariantError::new(path, format!(
+                    "duplicate element {:?}", elem))),
+                Ordering::Less => return Err(InvariantError::new(path, format!(
+                    "element {:?} is out of order, it comes after {:?}", elem, prev))),
+                Ordering::Greater => (),
+            }
+        }
+        prev = Some(elem);
+        count += 1;
+
+        if let Some(right) = node.child(1) {
+            node.check_link(1, &right)
+                .map_err(|reason| InvariantError::new(path.clone(), reason))?;
+            path.push(1);
+            next = Some((right, depth + 1));
+        }
+    }
+
+    if count != len {
+        return Err(InvariantError::new(Vec::new(), format!(
+            "stored length is {}, but the tree has {} nodes", len, count)));
+    }
+    Ok(())
+}
+
+#[test]
+fn check_finds_broken_nodes() {
+    // nodes in a vec, each linking back to the parent and branch it's under, so that a test
+    // can link them back wrongly, which trees with parent links could only do by a bug
+    struct Node {
+        elem: i32,
+        children: [Option<usize>; 2],
+        parent: Option<(usize, usize)>,
+    }
+    struct Ref<'t> {
+        nodes: &'t [Node],
+        index: usize,
+    }
+    impl<'t> NodeRef<'t, i32> for Ref<'t> {
+        fn elem(&self) -> &'t i32 {
+            &self.nodes[self.index].elem
+        }
+
+        fn child(&self, branch: usize) -> Option<Self> {
+            self.nodes[self.index].children[branch].map(|index| Ref {
+                nodes: self.nodes,
+                index,
+            })
+        }
+
+        fn check_link(&self, branch: usize, child: &Self) -> Result<(), String> {
+            match self.nodes[child.index].parent {
+                Some(parent) if parent == (self.index, branch) => Ok(()),
+                _ => Err(format!("child {} links back wrongly", branch)),
+            }
+        }
+    }
+    fn check_nodes(nodes: &[Node]) -> Result<(), InvariantError> {
+        check(Some(Ref { nodes, index: 0 }), nodes.len())
+    }
+
+    // 2 at the root, 1 and 3 below it, and 4 right of 3
+    let node = |elem, children, parent| Node { elem, children, parent };
+    let mut nodes = vec![node(2, [Some(1), Some(2)], None),
+                         node(1, [None, None], Some((0, 0))),
+                         node(3, [None, Some(3)], Some((0, 1))),
+                         node(4, [None, None], Some((2, 1)))];
+    assert_eq!(check_nodes(&nodes), Ok(()));
+    let path = |result: Result<(), InvariantError>| result.unwrap_err().path;
+
+    // a broken link is reported at the parent
+    for &wrong in &[(0, 1), (2, 0)] {
+        nodes[3].parent = Some(wrong);
+        assert_eq!(path(check_nodes(&nodes)), [1]);
+    }
+    nodes[3].parent = Some((2, 1));
+    nodes[1].parent = None;
+    assert!(path(check_nodes(&nodes)).is_empty());
+    nodes[1].parent = Some((0, 0));
+
+    // a misplaced or duplicate element is reported at the later of the pair in order
+    nodes[3].elem = 3;
+    assert_eq!(check_nodes(&nodes).unwrap_err(), InvariantError::new(vec![1, 1],
+               "duplicate element 3".to_owned()));
+    nodes[3].elem = 0;
+    assert_eq!(path(check_nodes(&nodes)), [1, 1]);
+    nodes[3].elem = 4;
+    nodes[1].elem = 5;
+    assert_eq!(check_nodes(&nodes).unwrap_err(), InvariantError::new(vec![],
+               "element 2 is out of order, it comes after 5".to_owned()));
+}
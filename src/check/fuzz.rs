@@ -0,0 +1,52 @@
+//! Differential checking driven by arbitrary bytes, for coverage-guided fuzzing.
+//!
+//! The libFuzzer target lives in `fuzz/`, and runs with `cargo fuzz run differential`.
+//! Its corpus is replayed by a plain test, so interesting inputs keep being checked
+//! without a fuzzer installed, and crashes can be replayed with `./bonzai-nbst replay`.
+
+use benchmark::Op;
+use super::{Divergence, check_ops, backends, reference};
+
+/// Decode every 3 bytes into an op: the low 2 bits of the first byte choose insert, remove
+/// or contains, and the next 2 bytes are the little endian key. If bit 2 of the first byte
+/// is set, the key is reduced mod 64, so that the fuzzer finds dense trees with repeated
+/// keys easily. A trailing partial op is ignored.
+pub fn decode_ops(data: &[u8]) -> Vec<Op<u16>> {
+    data.chunks(3)
+        .filter(|chunk| chunk.len() == 3)
+        .map(|chunk| {
+            let mut key = chunk[1] as u16 | (chunk[2] as u16) << 8;
+            if chunk[0] & 0b100 != 0 {
+                key %= 64;
+            }
+            match chunk[0] & 0b11 {
+                0 | 3 => Op::Insert(key),
+                1 => Op::Remove(key),
+                _ => Op::Contains(key),
+            }
+        })
+        .collect()
+}
+
+/// Run one fuzz input against every backend.
+pub fn check_input(data: &[u8]) -> Result<(), Divergence> {
+    check_ops(&decode_ops(data), &backends(), &reference())
+}
+
+#[test]
+fn replay_corpus() {
+    use std::fs;
+    use std::path::Path;
+
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/differential");
+    let mut replayed = 0;
+    for entry in fs::read_dir(&corpus).unwrap() {
+        let path = entry.unwrap().path();
+        let data = fs::read(&path).unwrap();
+        if let Err(divergence) = check_input(&data) {
+            panic!("{}: {}\n{}", path.display(), divergence, divergence.tree);
+        }
+        replayed += 1;
+    }
+    assert!(replayed > 0, "no corpus found in {}", corpus.display());
+}
@@ -0,0 +1,230 @@
+//! Checking that histories of concurrent operations are linearizable, by the backtracking
+//! search of Wing and Gong, with the memoization of Lowe.
+//!
+//! A history is linearizable if each operation can be given a point between its call and
+//! its return, such that running the operations one by one in that order, against a
+//! sequential model, gives every result the history recorded.
+
+use bst::Bst;
+use benchmark::Op;
+
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// One completed operation, with the logical times of its call and return.
+#[derive(Debug, Clone)]
+pub struct Event<T> {
+    pub thread: usize,
+    pub call: usize,
+    pub ret: usize,
+    pub op: Op<T>,
+    pub result: bool,
+}
+impl<T: Debug> Display for Event<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "thread {} [{}, {}] {:?} -> {}", self.thread, self.call, self.ret, self.op, self.result)
+    }
+}
+
+/// Records events from several threads against one clock.
+#[derive(Debug)]
+pub struct Recorder<T> {
+    clock: AtomicUsize,
+    events: Mutex<Vec<Event<T>>>,
+}
+impl<T> Recorder<T> {
+    pub fn new() -> Self {
+        Recorder {
+            clock: AtomicUsize::new(0),
+            events: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Run `op`, which performs the operation `op` describes, recording its result.
+    pub fn record<F: FnOnce() -> bool>(&self, thread: usize, op: Op<T>, run: F) -> bool {
+        let call = self.clock.fetch_add(1, Ordering::SeqCst);
+        let result = run();
+        let ret = self.clock.fetch_add(1, Ordering::SeqCst);
+        self.events.lock().unwrap().push(Event {
+            thread,
+            call,
+            ret,
+            op,
+            result,
+        });
+        result
+    }
+
+    pub fn into_history(self) -> Vec<Event<T>> {
+        let mut history = self.events.into_inner().unwrap();
+        history.sort_by_key(|event| event.call);
+        history
+    }
+}
+impl<T> Default for Recorder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NotLinearizable<T> {
+    pub history: Vec<Event<T>>,
+    /// The most operations which could be linearized, from the start of the history.
+    pub longest: usize,
+}
+impl<T: Debug> Display for NotLinearizable<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "history of {} operations isn't linearizable, at most {} of them can be",
+                 self.history.len(), self.longest)?;
+        for event in &self.history {
+            writeln!(f, "    {}", event)?;
+        }
+        Ok(())
+    }
+}
+
+/// Search for a linearization of the history, against a sequential `B` starting empty.
+pub fn check<T, B>(history: &[Event<T>]) -> Result<(), NotLinearizable<T>>
+    where T: Ord + Debug + Clone + Hash,
+          B: Bst<T> + Clone,
+          for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    let mut search = Search {
+        history,
+        done: vec![false; history.len()],
+        seen: HashSet::new(),
+        longest: 0,
+    };
+    if search.search(B::new(), 0) {
+        Ok(())
+    } else {
+        Err(NotLinearizable {
+            history: history.to_vec(),
+            longest: search.longest,
+        })
+    }
+}
+
+struct Search<'h, T: 'h> {
+    history: &'h [Event<T>],
+    done: Vec<bool>,
+    // linearizations already explored, by which ops they include and the state they reach
+    seen: HashSet<(Vec<bool>, Vec<T>)>,
+    longest: usize,
+}
+impl<'h, T: Ord + Debug + Clone + Hash> Search<'h, T> {
+    // recurses once per operation linearized, so is as deep as the history is long
+    fn search<B>(&mut self, model: B, num_done: usize) -> bool
+        where B: Bst<T> + Clone,
+              for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+        self.longest = self.longest.max(num_done);
+        if num_done == self.history.len() {
+            return true;
+        }
+        // an op can go next if it was called before every other pending op returned
+        let first_ret = self.history.iter()
+            .zip(&self.done)
+            .filter(|&(_, &done)| !done)
+            .map(|(event, _)| event.ret)
+            .min()
+            .unwrap();
+        for i in 0..self.history.len() {
+            let event = &self.history[i];
+            if self.done[i] || event.call > first_ret {
+                continue;
+            }
+            let mut next = model.clone();
+            let result = match event.op {
+                Op::Insert(ref t) => next.insert(t.clone()),
+                Op::Remove(ref t) => next.remove(t),
+                Op::Contains(ref t) => next.contains(t),
+            };
+            if result != event.result {
+                continue;
+            }
+            self.done[i] = true;
+            let state = (self.done.clone(), next.into_iter().cloned().collect());
+            if self.seen.insert(state) && self.search(next, num_done + 1) {
+                return true;
+            }
+            self.done[i] = false;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+fn event(thread: usize, call: usize, ret: usize, op: Op<i32>, result: bool) -> Event<i32> {
+    Event {
+        thread,
+        call,
+        ret,
+        op,
+        result,
+    }
+}
+
+#[test]
+fn rejects_stale_read() {
+    use bst::boxy::BoxBst;
+
+    // the contains starts after the insert returned, so must see it
+    let stale = vec![
+        event(0, 0, 1, Op::Insert(1), true),
+        event(1, 2, 3, Op::Contains(1), false),
+    ];
+    assert!(check::<i32, BoxBst<i32>>(&stale).is_err());
+
+    // but if they overlap, the contains can go first
+    let overlapping = vec![
+        event(0, 0, 2, Op::Insert(1), true),
+        event(1, 1, 3, Op::Contains(1), false),
+    ];
+    assert!(check::<i32, BoxBst<i32>>(&overlapping).is_ok());
+}
+
+#[test]
+fn lock_coupling_is_linearizable() {
+    use bst::boxy::BoxBst;
+    use bst::coupling::LockCouplingBst;
+    use super::gen_ops;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    const THREADS: usize = 4;
+    for seed in 0..50 {
+        let tree = Arc::new(LockCouplingBst::new());
+        let recorder = Arc::new(Recorder::new());
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let threads: Vec<_> = (0..THREADS).map(|t| {
+            let (tree, recorder, barrier) = (tree.clone(), recorder.clone(), barrier.clone());
+            // few keys, so that the threads collide
+            let ops = gen_ops(seed * THREADS as u64 + t as u64, 40, 8);
+            thread::spawn(move || {
+                barrier.wait();
+                for op in ops {
+                    recorder.record(t, op, || match op {
+                        Op::Insert(n) => tree.insert(n),
+                        Op::Remove(n) => tree.remove(&n),
+                        Op::Contains(n) => tree.contains(&n),
+                    });
+                }
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let history = Arc::try_unwrap(recorder).unwrap().into_history();
+        if let Err(e) = check::<i32, BoxBst<i32>>(&history) {
+            panic!("seed {}: {}", seed, e);
+        }
+        let elems = tree.elems();
+        assert!(elems.windows(2).all(|pair| pair[0] < pair[1]), "seed {}: {:?}", seed, elems);
+        assert_eq!(elems.len(), tree.len(), "seed {}", seed);
+    }
+}
@@ -0,0 +1,319 @@
+//! Randomized differential testing: every backend is driven through the same ops as a
+//! reference model, and must agree with it on every return value and on its contents.
+
+pub mod shrink;
+pub mod fuzz;
+pub mod linearizable;
+#[cfg(test)]
+mod props;
+#[cfg(test)]
+mod model;
+
+use bst::Bst;
+use bst::bonzai::BonzaiBst;
+use bst::boxy::BoxBst;
+use bst::persistent::PersistentBst;
+use bst::stdlib::BTreeSet;
+use bst::validate::InvariantError;
+use bst::pretty::PrettyConfig;
+use benchmark::{Op, seeded_rng};
+use benchmark::registry::select_by_name;
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::ops::Range;
+
+use rand::prelude::*;
+
+/// How many ops run between full comparisons of every tree against the model.
+const CHECK_EVERY: usize = 64;
+
+/// An object-safe view of a `Bst`, so that trees of different types can be driven side by
+/// side.
+pub trait DynBst<T>: Debug {
+    fn insert(&mut self, elem: T) -> bool;
+
+    fn remove(&mut self, elem: &T) -> bool;
+
+    fn contains(&self, elem: &T) -> bool;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn validate(&self) -> Result<(), InvariantError>;
+
+    /// The elements in iteration order.
+    fn elems(&self) -> Vec<T>;
+
+    fn dump(&self) -> String;
+}
+impl<T: Ord + Debug + Clone, B: Bst<T>> DynBst<T> for B
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    fn insert(&mut self, elem: T) -> bool {
+        Bst::insert(self, elem)
+    }
+
+    fn remove(&mut self, elem: &T) -> bool {
+        Bst::remove(self, elem)
+    }
+
+    fn contains(&self, elem: &T) -> bool {
+        Bst::contains(self, elem)
+    }
+
+    fn len(&self) -> usize {
+        Bst::len(self)
+    }
+
+    fn validate(&self) -> Result<(), InvariantError> {
+        Bst::validate(self)
+    }
+
+    fn elems(&self) -> Vec<T> {
+        self.into_iter().cloned().collect()
+    }
+
+    fn dump(&self) -> String {
+        dump(self)
+    }
+}
+
+/// The tree drawn sideways for failure output, or its debug form if it doesn't expose its
+/// shape.
+pub fn dump<T: Ord + Debug, B: Bst<T>>(tree: &B) -> String
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    tree.display(&PrettyConfig::default()).to_string()
+}
+
+/// A named constructor for one backend.
+pub struct Subject<T> {
+    pub name: &'static str,
+    new: fn() -> Box<dyn DynBst<T>>,
+}
+
+fn new_boxed<T: Ord + Debug + Clone + 'static, B: Bst<T> + 'static>() -> Box<dyn DynBst<T>>
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    Box::new(B::new())
+}
+
+pub fn subject<T: Ord + Debug + Clone + 'static, B: Bst<T> + 'static>(name: &'static str)
+    -> Subject<T>
+    where for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    Subject {
+        name,
+        new: new_boxed::<T, B>,
+    }
+}
+
+/// Every backend under test.
+pub fn backends<T: Ord + Debug + Clone + 'static>() -> Vec<Subject<T>> {
+    vec![
+        subject::<T, BonzaiBst<T>>("bonzai"),
+        subject::<T, BoxBst<T>>("boxy"),
+        subject::<T, PersistentBst<T>>("persistent"),
+    ]
+}
+
+/// The backends with the given names, in the order given.
+pub fn select<T: Ord + Debug + Clone + 'static>(names: &[String]) -> Result<Vec<Subject<T>>, String> {
+    select_by_name(backends(), names, |subject| subject.name)
+}
+
+/// The model which the backends are checked against.
+pub fn reference<T: Ord + Debug + Clone + 'static>() -> Subject<T> {
+    subject::<T, BTreeSet<T>>("btree")
+}
+
+/// The first point at which a backend disagreed with the reference model.
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub backend: &'static str,
+    /// The index of the op after which the disagreement was first visible.
+    pub index: usize,
+    pub op: String,
+    pub reason: String,
+    /// The diverging tree, as it was after the op.
+    pub tree: String,
+}
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} diverged at op {} ({}): {}",
+               self.backend, self.index, self.op, self.reason)
+    }
+}
+
+fn apply<T: Clone>(tree: &mut dyn DynBst<T>, op: &Op<T>) -> bool {
+    match *op {
+        Op::Insert(ref t) => tree.insert(t.clone()),
+        Op::Remove(ref t) => tree.remove(t),
+        Op::Contains(ref t) => tree.contains(t),
+    }
+}
+
+fn consistent<T: Ord + Debug>(tree: &dyn DynBst<T>, model: &dyn DynBst<T>) -> Result<(), String> {
+    tree.validate().map_err(|e| format!("invariant broken {}", e))?;
+    let (elems, expected) = (tree.elems(), model.elems());
+    if elems != expected {
+        let missing: Vec<&T> = expected.iter()
+            .filter(|t| !elems.contains(t))
+            .collect();
+        let unexpected: Vec<&T> = elems.iter()
+            .filter(|t| !expected.contains(t))
+            .collect();
+        if missing.is_empty() && unexpected.is_empty() {
+            return Err(format!("iterated as {:?}, expected {:?}", elems, expected));
+        }
+        return Err(format!("missing {:?}, unexpected {:?}", missing, unexpected));
+    }
+    if tree.len() != model.len() {
+        return Err(format!("len is {}, expected {}", tree.len(), model.len()));
+    }
+    Ok(())
+}
+
+fn run<T: Ord + Debug + Clone>(ops: &[Op<T>], subjects: &[Subject<T>], reference: &Subject<T>,
+                                check_every: usize) -> Result<(), Divergence> {
+    let mut model = (reference.new)();
+    let mut trees: Vec<Box<dyn DynBst<T>>> = subjects.iter()
+        .map(|subject| (subject.new)())
+        .collect();
+    for (i, op) in ops.iter().enumerate() {
+        let expected = apply(&mut *model, op);
+        let check = (i + 1) % check_every == 0 || i + 1 == ops.len();
+        for (subject, tree) in subjects.iter().zip(trees.iter_mut()) {
+            let returned = apply(&mut **tree, op);
+            let result = if returned != expected {
+                Err(format!("returned {}, but {} returned {}",
+                            returned, reference.name, expected))
+            } else if check {
+                consistent(&**tree, &*model)
+            } else {
+                Ok(())
+            };
+            if let Err(reason) = result {
+                return Err(Divergence {
+                    backend: subject.name,
+                    index: i,
+                    op: format!("{:?}", op),
+                    reason,
+                    tree: tree.dump(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run the ops against every subject and the reference, and find the first op after which
+/// any subject disagrees with the reference.
+pub fn check_ops<T: Ord + Debug + Clone>(ops: &[Op<T>], subjects: &[Subject<T>],
+                                         reference: &Subject<T>) -> Result<(), Divergence> {
+    match run(ops, subjects, reference, CHECK_EVERY) {
+        Ok(()) => Ok(()),
+        Err(divergence) => {
+            // the state may have diverged anywhere since the last full comparison, so
+            // replay up to here comparing after every op
+            let replay = &ops[..divergence.index + 1];
+            Err(run(replay, subjects, reference, 1).err().unwrap_or(divergence))
+        },
+    }
+}
+
+/// Random ops over keys in `-key_range..key_range`, with the same mix as the old
+/// `cross_check`: removes and re-inserts of present keys keep the tree from just growing.
+pub fn gen_ops(seed: u64, num_ops: usize, key_range: i32) -> Vec<Op<i32>> {
+    assert!(key_range > 0, "ops over an empty key range");
+    let mut rng = seeded_rng(seed);
+    let mut present = BTreeSet::new();
+    let mut ops = Vec::with_capacity(num_ops);
+    while ops.len() < num_ops {
+        let existing = if present.is_empty() {
+            None
+        } else {
+            present.iter().cloned().nth(rng.gen::<usize>() % present.len())
+        };
+        let op = match (rng.gen::<u8>() % 6, existing) {
+            (0, _) | (1, _) => Op::Insert(rng.gen::<i32>() % key_range),
+            (2, _) => Op::Remove(rng.gen::<i32>() % key_range),
+            (3, _) => Op::Contains(rng.gen::<i32>() % key_range),
+            (4, Some(n)) => Op::Remove(n),
+            (5, Some(n)) => Op::Insert(n),
+            _ => continue,
+        };
+        match op {
+            Op::Insert(n) => {
+                present.insert(n);
+            },
+            Op::Remove(n) => {
+                present.remove(&n);
+            },
+            Op::Contains(_) => (),
+        };
+        ops.push(op);
+    }
+    ops
+}
+
+#[derive(Debug, Clone)]
+pub struct SeedFailure {
+    pub seed: u64,
+    pub num_ops: usize,
+    pub divergence: Divergence,
+    /// The smallest sequence of ops found to reproduce the divergence.
+    pub shrunk: Vec<Op<i32>>,
+    pub shrunk_divergence: Divergence,
+}
+impl Display for SeedFailure {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "seed {} ({} ops): {}", self.seed, self.num_ops, self.divergence)?;
+        writeln!(f, "shrunk to {} ops: {}", self.shrunk.len(), self.shrunk_divergence)?;
+        writeln!(f, "{}", self.shrunk_divergence.tree)?;
+        writeln!(f)?;
+        let name = format!("seed_{}_{}", self.seed, self.divergence.backend);
+        write!(f, "{}", shrink::as_test(&name, &self.shrunk))
+    }
+}
+
+/// Check each seed's ops in turn, stopping at the first failure.
+pub fn check_seeds(seeds: Range<u64>, num_ops: usize, key_range: i32, subjects: &[Subject<i32>],
+                   reference: &Subject<i32>) -> Result<(), SeedFailure> {
+    for seed in seeds {
+        let mut ops = gen_ops(seed, num_ops, key_range);
+        if let Err(divergence) = check_ops(&ops, subjects, reference) {
+            // only keep shrinks which reproduce the same backend's divergence, so that the
+            // search doesn't wander off to some other bug
+            let backend = divergence.backend;
+            let diverges = |ops: &[Op<i32>]| match check_ops(ops, subjects, reference) {
+                Err(divergence) => divergence.backend == backend,
+                Ok(()) => false,
+            };
+            ops.truncate(divergence.index + 1);
+            let shrunk = shrink::shrink(ops, diverges);
+            let shrunk_divergence = check_ops(&shrunk, subjects, reference).unwrap_err();
+            return Err(SeedFailure {
+                seed,
+                num_ops,
+                divergence,
+                shrunk,
+                shrunk_divergence,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn backends_agree_with_reference() {
+    // a small key range keeps the trees dense, so removes of present keys are common
+    for &(num_ops, key_range) in &[(10000, 1000), (2000, 50)] {
+        if let Err(failure) = check_seeds(0..32, num_ops, key_range, &backends(), &reference()) {
+            panic!("{}", failure);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Exhaustive small-scope checking: every sequence of inserts and removes up to some length,
+//! over a handful of keys, compared step by step with `BTreeSet`.
+//!
+//! Random ops rarely hit every combination of the removal cases, such as removing a node
+//! with two children whose right child has or hasn't got a left subtree, at every depth.
+//! Small trees are enough to reach all of them.
+
+use bst::Bst;
+use bst::bonzai::BonzaiBst;
+use bst::boxy::BoxBst;
+use bst::persistent::PersistentBst;
+use bst::stdlib::BTreeSet;
+use benchmark::Op;
+use super::dump;
+
+/// Check every sequence of `len` inserts and removes of the keys `0..keys`, and so every
+/// shorter sequence as a prefix of one of them. After each step, the return value,
+/// `contains` of every key, `len`, `validate` and iteration are compared with the model.
+pub fn check_all<B: Bst<i32>>(len: usize, keys: i32) -> Result<(), String>
+    where for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    // each sequence is a number in base 2 * keys, one digit per op
+    let mut digits = vec![0; len];
+    loop {
+        let ops: Vec<Op<i32>> = digits.iter()
+            .map(|&d| if d < keys { Op::Insert(d) } else { Op::Remove(d - keys) })
+            .collect();
+        check_sequence::<B>(&ops, keys)?;
+
+        // increment, least significant digit last so sequences are visited in order
+        match digits.iter().rposition(|&d| d + 1 < 2 * keys) {
+            Some(i) => {
+                digits[i] += 1;
+                for d in &mut digits[i + 1..] {
+                    *d = 0;
+                }
+            },
+            None => return Ok(()),
+        };
+    }
+}
+
+fn check_sequence<B: Bst<i32>>(ops: &[Op<i32>], keys: i32) -> Result<(), String>
+    where for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    let mut tree = B::new();
+    let mut model = BTreeSet::new();
+    for (i, op) in ops.iter().enumerate() {
+        let (returned, expected) = match *op {
+            Op::Insert(t) => (tree.insert(t), model.insert(t)),
+            Op::Remove(t) => (tree.remove(&t), model.remove(&t)),
+            Op::Contains(t) => (tree.contains(&t), model.contains(&t)),
+        };
+        let fail = |what: String| {
+            Err(format!("after {:?}: {}\n{}", &ops[..i + 1], what, dump(&tree)))
+        };
+        if returned != expected {
+            return fail(format!("returned {}, expected {}", returned, expected));
+        }
+        if let Err(e) = tree.validate() {
+            return fail(format!("invariant broken {}", e));
+        }
+        if let Some(k) = (0..keys).find(|k| tree.contains(k) != model.contains(k)) {
+            return fail(format!("contains({}) is {}", k, tree.contains(&k)));
+        }
+        if tree.len() != model.len() || !tree.into_iter().eq(model.iter()) {
+            return fail(format!("holds {:?}, expected {:?}",
+                                tree.into_iter().collect::<Vec<_>>(), model));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn bonzai_small_scope() {
+    if let Err(e) = check_all::<BonzaiBst<i32>>(6, 4) {
+        panic!("{}", e);
+    }
+}
+
+#[test]
+fn boxy_small_scope() {
+    if let Err(e) = check_all::<BoxBst<i32>>(6, 4) {
+        panic!("{}", e);
+    }
+}
+
+#[test]
+fn persistent_small_scope() {
+    if let Err(e) = check_all::<PersistentBst<i32>>(6, 4) {
+        panic!("{}", e);
+    }
+}
+
+// ten million sequences, so run it in release with --ignored
+#[test]
+#[ignore]
+fn bonzai_larger_scope() {
+    if let Err(e) = check_all::<BonzaiBst<i32>>(7, 5) {
+        panic!("{}", e);
+    }
+}
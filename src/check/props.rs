@@ -0,0 +1,145 @@
+//! The contract every `Bst` has to keep, checked generically, so that a new backend is
+//! covered by adding one test below.
+
+use bst::Bst;
+use bst::bonzai::BonzaiBst;
+use bst::boxy::BoxBst;
+use bst::persistent::PersistentBst;
+use bst::stdlib::BTreeSet;
+use bst::pretty::PrettyConfig;
+use benchmark::Op;
+use super::{CHECK_EVERY, gen_ops};
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::iter::FromIterator;
+
+/// Apply the ops, checking every return value against the `BTreeSet` model, and
+/// periodically checking that iteration is sorted, duplicate-free and agrees with `len`.
+/// Finally, check clones and snapshots, and round trips through `FromIterator`.
+pub fn check_contract<T, B>(ops: &[Op<T>]) -> Result<(), String>
+    where T: Ord + Debug + Clone,
+          B: Bst<T> + FromIterator<T> + Clone,
+          for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    let mut tree = B::new();
+    let mut model = BTreeSet::new();
+    for (i, op) in ops.iter().enumerate() {
+        let present = model.contains(op.key());
+        let (returned, expected, now_present) = match *op {
+            Op::Insert(ref t) => {
+                model.insert(t.clone());
+                (tree.insert(t.clone()), !present, true)
+            },
+            Op::Remove(ref t) => {
+                model.remove(t);
+                (tree.remove(t), present, false)
+            },
+            Op::Contains(ref t) => (tree.contains(t), present, present),
+        };
+        let fail = |what: String| Err(format!("op {} ({:?}): {}", i, op, what));
+        if returned != expected {
+            return fail(format!("returned {}, but the element was {}",
+                                returned, if present { "present" } else { "absent" }));
+        }
+        if tree.contains(op.key()) != now_present {
+            return fail(format!("contains is {} afterwards", !now_present));
+        }
+        if tree.len() != model.len() {
+            return fail(format!("len is {}, expected {}", tree.len(), model.len()));
+        }
+        if (i + 1) % CHECK_EVERY == 0 || i + 1 == ops.len() {
+            if let Err(what) = check_iteration(&tree) {
+                return fail(what);
+            }
+        }
+    }
+
+    let copy = tree.clone();
+    if let Err(e) = copy.validate() {
+        return Err(format!("clone is invalid: {}", e));
+    }
+    if !copy.into_iter().eq(tree.into_iter()) || copy.len() != tree.len() {
+        return Err("clone has different elements".to_owned());
+    }
+    let config = PrettyConfig::default();
+    if copy.pretty(&config) != tree.pretty(&config) {
+        return Err("clone has a different shape".to_owned());
+    }
+    let snapshot = tree.snapshot();
+    for op in ops {
+        tree.remove(op.key());
+    }
+    if !snapshot.iter().eq(copy.into_iter()) || snapshot.len() != copy.len() {
+        return Err("removing every element from the tree changed its snapshot".to_owned());
+    }
+    tree = copy;
+
+    let rebuilt: B = tree.into_iter().cloned().collect();
+    if !rebuilt.into_iter().eq(tree.into_iter()) || rebuilt.len() != tree.len() {
+        return Err("collecting the tree's elements gave a different tree".to_owned());
+    }
+
+    // keys in op order, so out of order and repeated
+    let collected: B = ops.iter().map(|op| op.key().clone()).collect();
+    let expected: BTreeSet<T> = ops.iter().map(|op| op.key().clone()).collect();
+    check_iteration(&collected)?;
+    if !collected.into_iter().eq(expected.iter()) {
+        return Err("collecting the ops' keys gave different elements than a BTreeSet".to_owned());
+    }
+    Ok(())
+}
+
+fn check_iteration<T, B>(tree: &B) -> Result<(), String>
+    where T: Ord + Debug,
+          B: Bst<T>,
+          for<'s> &'s B: IntoIterator<Item = &'s T> {
+
+    let elems: Vec<&T> = tree.into_iter().collect();
+    for pair in elems.windows(2) {
+        match pair[0].cmp(pair[1]) {
+            Ordering::Equal => return Err(format!("iteration repeats {:?}", pair[0])),
+            Ordering::Greater => return Err(format!("iteration yields {:?} before {:?}",
+                                                    pair[0], pair[1])),
+            Ordering::Less => {},
+        };
+    }
+    if elems.len() != tree.len() {
+        return Err(format!("iteration yields {} elements, but len is {}", elems.len(), tree.len()));
+    }
+    Ok(())
+}
+
+fn keeps_contract<B>()
+    where B: Bst<i32> + FromIterator<i32> + Clone,
+          for<'s> &'s B: IntoIterator<Item = &'s i32> {
+
+    for seed in 0..16 {
+        for &key_range in &[1000, 30] {
+            let ops = gen_ops(seed, 2000, key_range);
+            if let Err(e) = check_contract::<i32, B>(&ops) {
+                panic!("seed {}, keys in -{}..{}: {}", seed, key_range, key_range, e);
+            }
+        }
+    }
+}
+
+#[test]
+fn bonzai_keeps_contract() {
+    keeps_contract::<BonzaiBst<i32>>();
+}
+
+#[test]
+fn boxy_keeps_contract() {
+    keeps_contract::<BoxBst<i32>>();
+}
+
+#[test]
+fn persistent_keeps_contract() {
+    keeps_contract::<PersistentBst<i32>>();
+}
+
+#[test]
+fn btree_keeps_contract() {
+    keeps_contract::<BTreeSet<i32>>();
+}
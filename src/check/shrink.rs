@@ -0,0 +1,99 @@
+//! Shrinking failing op sequences down to something small enough to debug by hand.
+
+use benchmark::Op;
+
+/// Shrink a failing sequence of ops to one which still fails, but where removing any single
+/// op or simplifying any single key would make it pass.
+pub fn shrink<F: FnMut(&[Op<i32>]) -> bool>(mut ops: Vec<Op<i32>>, mut fails: F) -> Vec<Op<i32>> {
+    while remove_chunks(&mut ops, &mut fails) | simplify_keys(&mut ops, &mut fails) {}
+    ops
+}
+
+// try removing ever smaller chunks, down to single ops
+fn remove_chunks<F: FnMut(&[Op<i32>]) -> bool>(ops: &mut Vec<Op<i32>>, fails: &mut F) -> bool {
+    let mut changed = false;
+    let mut chunk = (ops.len() / 2).max(1);
+    loop {
+        let mut start = 0;
+        while start < ops.len() {
+            let end = (start + chunk).min(ops.len());
+            let mut candidate = ops[..start].to_vec();
+            candidate.extend_from_slice(&ops[end..]);
+            if fails(&candidate) {
+                *ops = candidate;
+                changed = true;
+            } else {
+                start += chunk;
+            }
+        }
+        if chunk == 1 {
+            return changed;
+        }
+        chunk /= 2;
+    }
+}
+
+// try moving each key towards zero
+fn simplify_keys<F: FnMut(&[Op<i32>]) -> bool>(ops: &mut Vec<Op<i32>>, fails: &mut F) -> bool {
+    let mut changed = false;
+    for i in 0..ops.len() {
+        let key = *ops[i].key();
+        for &simpler in &[0, key / 2, key - key.signum()] {
+            if simpler == key {
+                continue;
+            }
+            let mut candidate = ops.clone();
+            *key_mut(&mut candidate[i]) = simpler;
+            if fails(&candidate) {
+                *ops = candidate;
+                changed = true;
+                break;
+            }
+        }
+    }
+    changed
+}
+
+fn key_mut<T>(op: &mut Op<T>) -> &mut T {
+    match op {
+        &mut Op::Insert(ref mut t) | &mut Op::Remove(ref mut t) | &mut Op::Contains(ref mut t) => t,
+    }
+}
+
+/// Render the ops as a test which replays them against every backend, ready to be pasted
+/// anywhere in the crate.
+pub fn as_test(name: &str, ops: &[Op<i32>]) -> String {
+    let mut test = format!("#[test]\nfn {}() {{\n", name);
+    test.push_str("    use benchmark::Op;\n");
+    test.push_str("    use check::{check_ops, backends, reference};\n\n");
+    test.push_str("    let ops = vec![\n");
+    for op in ops {
+        test.push_str(&format!("        Op::{:?},\n", op));
+    }
+    test.push_str("    ];\n");
+    test.push_str("    if let Err(divergence) = check_ops(&ops, &backends(), &reference()) {\n");
+    test.push_str("        panic!(\"{}\", divergence);\n");
+    test.push_str("    }\n");
+    test.push_str("}\n");
+    test
+}
+
+#[test]
+fn shrinks_to_minimal_sequence() {
+    // fails whenever 7 is inserted and then removed
+    let fails = |ops: &[Op<i32>]| {
+        ops.iter()
+            .position(|op| match *op { Op::Insert(7) => true, _ => false })
+            .map(|i| ops[i..].iter().any(|op| match *op { Op::Remove(7) => true, _ => false }))
+            .unwrap_or(false)
+    };
+    let ops = (0..200)
+        .map(|n| match n % 3 {
+            0 => Op::Insert(n % 10),
+            1 => Op::Remove(n % 10),
+            _ => Op::Contains(n),
+        })
+        .collect();
+    let shrunk = shrink(ops, fails);
+    assert_eq!(format!("{:?}", shrunk), "[Insert(7), Remove(7)]");
+}
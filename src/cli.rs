@@ -0,0 +1,221 @@
+use benchmark::DEFAULT_SEED;
+use benchmark::stats::RunConfig;
+use benchmark::output::Format;
+use benchmark::compare::CompareConfig;
+use bst::dot::Annotation;
+
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+use: ./bonzai-nbst [num_ops] [--threads n] [options]
+     ./bonzai-nbst compare [baseline.json] [--threshold percent] [--alpha p] [options]
+     ./bonzai-nbst sweep [min_ops] [max_ops] [--factor f] [options]
+     ./bonzai-nbst check [num_seeds] [num_ops] [--seed first] [--backends a,b,..]
+     ./bonzai-nbst replay [fuzz_input]..
+     ./bonzai-nbst dot [num_ops] [--annotate height|size] [--seed n] [--backends a,b,..]
+options: [--warmup n] [--reps n] [--latency] [--backends a,b,..]
+         [--format json|csv|table] [--seed n]";
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Benchmark `num_ops` random ops.
+    Bench {
+        num_ops: usize,
+    },
+    /// Re-run the workloads in a json results file, and compare against its timings.
+    Compare {
+        baseline: String,
+    },
+    /// Benchmark random ops at a geometric series of sizes.
+    Sweep {
+        min: usize,
+        max: usize,
+    },
+    /// Differentially test the backends against `BTreeSet` on random ops from each seed.
+    Check {
+        seeds: u64,
+        num_ops: usize,
+    },
+    /// Differentially test the backends on the ops decoded from fuzz inputs.
+    Replay {
+        inputs: Vec<String>,
+    },
+    /// Print graphviz digraphs of the trees left by `num_ops` random ops.
+    Dot {
+        num_ops: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub command: Command,
+    pub warmup: Option<usize>,
+    pub reps: Option<usize>,
+    pub latency: bool,
+    /// Backend names to run, or all registered backends if not given.
+    pub backends: Option<Vec<String>>,
+    pub format: Format,
+    pub seed: u64,
+    pub threshold: Option<f64>,
+    pub alpha: Option<f64>,
+    pub factor: f64,
+    pub annotation: Option<Annotation>,
+    /// Benchmark a tree shared between this many threads, each running its own ops.
+    pub threads: Option<usize>,
+}
+impl Options {
+    pub fn run_config(&self) -> RunConfig {
+        let default = RunConfig::default();
+        RunConfig {
+            warmup: self.warmup.unwrap_or(default.warmup),
+            repetitions: self.reps.unwrap_or(default.repetitions),
+        }
+    }
+
+    pub fn compare_config(&self) -> CompareConfig {
+        let default = CompareConfig::default();
+        CompareConfig {
+            threshold: self.threshold.map(|percent| percent / 100.0).unwrap_or(default.threshold),
+            alpha: self.alpha.unwrap_or(default.alpha),
+            warmup: self.warmup,
+            repetitions: self.reps,
+        }
+    }
+}
+
+// one flat match over every flag, which splitting up would only obscure
+#[allow(clippy::cognitive_complexity)]
+pub fn parse(args: &[String]) -> Result<Options, String> {
+    let mut positional: Vec<&str> = Vec::new();
+    let mut options = Options {
+        command: Command::Bench {
+            num_ops: 0,
+        },
+        warmup: None,
+        reps: None,
+        latency: false,
+        backends: None,
+        format: Format::Table,
+        seed: DEFAULT_SEED,
+        threshold: None,
+        alpha: None,
+        factor: 2.0,
+        annotation: None,
+        threads: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--warmup" => {
+                options.warmup = Some(flag_value(arg, args.next())?);
+            },
+            "--reps" => {
+                options.reps = Some(flag_value(arg, args.next())?);
+            },
+            "--latency" => {
+                options.latency = true;
+            },
+            "--backends" => {
+                let list: String = flag_value(arg, args.next())?;
+                options.backends = Some(list.split(',')
+                    .map(|name| name.trim().to_owned())
+                    .filter(|name| !name.is_empty())
+                    .collect());
+            },
+            "--format" => {
+                options.format = flag_value(arg, args.next())?;
+            },
+            "--seed" => {
+                options.seed = flag_value(arg, args.next())?;
+            },
+            "--threshold" => {
+                options.threshold = Some(flag_value(arg, args.next())?);
+            },
+            "--alpha" => {
+                options.alpha = Some(flag_value(arg, args.next())?);
+            },
+            "--factor" => {
+                options.factor = flag_value(arg, args.next())?;
+            },
+            "--annotate" => {
+                options.annotation = Some(flag_value(arg, args.next())?);
+            },
+            "--threads" => {
+                options.threads = Some(flag_value(arg, args.next())?);
+            },
+            other if !other.starts_with("--") => {
+                positional.push(other);
+            },
+            other => {
+                return Err(format!("unexpected argument: {}", other));
+            }
+        };
+    }
+
+    options.command = match positional.as_slice() {
+        &["compare", baseline] => Command::Compare {
+            baseline: baseline.to_owned(),
+        },
+        &["compare"] => {
+            return Err("compare requires a baseline results file".to_owned());
+        },
+        &["sweep", min, max] => Command::Sweep {
+            min: min.parse().map_err(|_| format!("invalid min ops: {}", min))?,
+            max: max.parse().map_err(|_| format!("invalid max ops: {}", max))?,
+        },
+        &["sweep", ..] => {
+            return Err("sweep requires a min and max number of ops".to_owned());
+        },
+        &["check", seeds, num_ops] => Command::Check {
+            seeds: seeds.parse().map_err(|_| format!("invalid num seeds: {}", seeds))?,
+            num_ops: num_ops.parse().map_err(|_| format!("invalid num ops: {}", num_ops))?,
+        },
+        &["check", ..] => {
+            return Err("check requires a number of seeds and of ops".to_owned());
+        },
+        &["dot", num_ops] => Command::Dot {
+            num_ops: num_ops.parse().map_err(|_| format!("invalid num ops: {}", num_ops))?,
+        },
+        &["dot", ..] => {
+            return Err("dot requires a number of ops".to_owned());
+        },
+        &["replay"] => {
+            return Err("replay requires at least one fuzz input".to_owned());
+        },
+        inputs if inputs.first() == Some(&"replay") => Command::Replay {
+            inputs: inputs[1..].iter().map(|&input| input.to_owned()).collect(),
+        },
+        &[num_ops] => Command::Bench {
+            num_ops: num_ops.parse()
+                .map_err(|_| format!("invalid num ops: {}", num_ops))?,
+        },
+        &[] => {
+            return Err("missing num ops".to_owned());
+        },
+        other => {
+            return Err(format!("unexpected arguments: {}", other.join(" ")));
+        }
+    };
+
+    if options.reps == Some(0) {
+        return Err("--reps must be at least 1".to_owned());
+    }
+    if options.threads == Some(0) {
+        return Err("--threads must be at least 1".to_owned());
+    }
+    if options.factor.is_nan() || options.factor <= 1.0 {
+        return Err("--factor must be greater than 1".to_owned());
+    }
+    if let Command::Sweep { min, max } = options.command {
+        if min == 0 || min > max {
+            return Err("sweep requires 0 < min_ops <= max_ops".to_owned());
+        }
+    }
+    Ok(options)
+}
+
+fn flag_value<V: FromStr>(flag: &str, value: Option<&String>) -> Result<V, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", flag))?;
+    value.parse().map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
@@ -0,0 +1,15 @@
+#![feature(nll)]
+
+extern crate bonzai;
+extern crate rand;
+extern crate stopwatch;
+extern crate memmap;
+#[cfg(feature = "serde")]
+extern crate serde;
+extern crate serde_json;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+pub mod bst;
+pub mod benchmark;
+pub mod check;
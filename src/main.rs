@@ -1,116 +1,282 @@
 
-#![feature(nll)]
-#![feature(duration_as_u128)]
+extern crate bonzai_nbst;
 
-extern crate bonzai;
-extern crate rand;
-extern crate stopwatch;
+mod cli;
 
-mod bst;
-mod benchmark;
+use bonzai_nbst::{bst, benchmark, check};
+use benchmark::{registry, compare, sweep, threaded};
+use benchmark::threaded::Sharing;
+use benchmark::registry::Backend;
+use benchmark::output::{self, BenchResult, Format};
+use cli::Command;
 
-use bst::Bst;
-
-use std::collections::HashSet;
 use std::env::args;
+use std::io;
+use std::fs;
+use std::process;
+
+#[global_allocator]
+static ALLOC: benchmark::memory::TrackingAlloc = benchmark::memory::TrackingAlloc;
 
-use rand::prelude::*;
-use rand::XorShiftRng;
-
-
-pub fn cross_check<A: Bst<i32>, B: Bst<i32>>()
-    where for<'s> &'s A: IntoIterator<Item = &'s i32> ,
-          for<'s> &'s B: IntoIterator<Item = &'s i32> {
-
-    let seed = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
-    let mut rng = XorShiftRng::from_seed(seed);
-
-    let mut a = A::new();
-    let mut b = B::new();
-    let mut h = HashSet::new();
-
-    for i in 0..10000 {
-        match rng.gen::<u8>() % 6 {
-            0 | 1 => {
-                // insert random element
-                let n: i32 = rng.gen::<i32>() % 1000;
-                a.insert(n);
-                b.insert(n);
-                h.insert(n);
-            },
-            2 => {
-                // remove random element
-                let n: i32 = rng.gen::<i32>() % 1000;
-                a.remove(&n);
-                b.remove(&n);
-                h.remove(&n);
-            },
-            3 => {
-                // check consistency
-                let mut a_size = 0;
-                for n in &a {
-                    assert!(a.contains(n));
-                    if !b.contains(n) {
-                        eprintln!("b doesn't contain {}", n);
-                        eprintln!("{:#?}", b);
-                        return;
-                    }
-                    a_size += 1;
-                }
-                let mut b_size = 0;
-                for n in &b {
-                    assert!(a.contains(n));
-                    assert!(b.contains(n));
-                    b_size += 1;
-                }
-                assert_eq!(a_size, b_size);
-                let h_size = h.len();
-                assert_eq!(a_size, h_size);
-                assert_eq!(b_size, h_size);
-                println!("i={}, all consistent", i);
-            },
-            4 => {
-                // remove random pre-existent element
-                let n: Option<i32> = h.iter().cloned().nth(rng.gen::<usize>() & h.len());
-                if let Some(n) = n {
-                    a.remove(&n);
-                    b.remove(&n);
-                    h.remove(&n);
-                }
-            },
-            5 => {
-                // insert random pre-existent element
-                let n: Option<i32> = h.iter().cloned().nth(rng.gen::<usize>() & h.len());
-                if let Some(n) = n {
-                    a.insert(n);
-                    b.insert(n);
-                    h.insert(n);
-                }
+fn main() {
+    let args: Vec<String> = args().skip(1).collect();
+    let options = match cli::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    let backends = match options.backends {
+        Some(ref names) => match registry::select(names) {
+            Ok(backends) => backends,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
             }
-            _ => unreachable!()
+        },
+        None => registry::registry(),
+    };
+
+    match options.command {
+        Command::Bench { num_ops } => match options.threads {
+            Some(threads) => bench_threaded(&options, &backends, num_ops, threads),
+            None => bench(&options, &backends, num_ops),
+        },
+        Command::Compare { ref baseline } => {
+            let code = compare(&options, &backends, baseline);
+            process::exit(code);
+        },
+        Command::Sweep { min, max } => sweep(&options, &backends, min, max),
+        Command::Check { seeds, num_ops } => {
+            let code = check(&options, seeds, num_ops);
+            process::exit(code);
+        },
+        Command::Dot { num_ops } => dot(&options, &backends, num_ops),
+        Command::Replay { ref inputs } => {
+            let code = replay(inputs);
+            process::exit(code);
+        },
+    };
+}
+
+fn bench(options: &cli::Options, backends: &[Box<dyn Backend>], num_ops: usize) {
+    let workload = benchmark::Workload::rand_ops(num_ops, options.seed);
+    let ops = workload.ops().unwrap();
+    let counts = benchmark::OpCounts::of(&ops);
+    let run = options.run_config();
+
+    let mut results = Vec::new();
+    for backend in backends {
+        results.push(BenchResult {
+            backend: backend.name().to_owned(),
+            workload: workload.clone(),
+            counts,
+            config: run,
+            summary: backend.run(&ops, &run),
+            memory: Some(backend.memory(&ops)),
+            shape: backend.shape(&ops),
+        });
+    }
+    output::write(&mut io::stdout(), options.format, &results)
+        .expect("failed to write results");
+
+    if options.latency {
+        for backend in backends {
+            let timing = backend.time_ms(&ops);
+            // keep machine-readable stdout clean
+            let report = format!("{} per-op latency ({} ms instrumented):\n{}",
+                                 backend.name(), timing.total_ms, timing.latencies);
+            match options.format {
+                Format::Table => println!("{}", report),
+                _ => eprintln!("{}", report),
+            };
+        }
+    }
+}
+
+/// Benchmark each backend shared between threads, first behind one lock, then sharded with
+/// a shard per thread, and then the lock coupling tree. Each thread runs `num_ops` ops of
+/// its own.
+fn bench_threaded(options: &cli::Options, backends: &[Box<dyn Backend>], num_ops: usize,
+                  threads: usize) {
+    let workload = benchmark::Workload::rand_ops(num_ops, options.seed);
+    let streams = threaded::streams(threads, num_ops, options.seed);
+    let counts = threaded::counts(&streams);
+    let run = options.run_config();
+
+    let mut results = Vec::new();
+    for backend in backends {
+        for &(sharing, mode) in &[(Sharing::Locked, "locked"), (Sharing::Sharded(threads), "sharded")] {
+            results.push(BenchResult {
+                backend: format!("{}/{}", backend.name(), mode),
+                workload: workload.clone(),
+                counts,
+                config: run,
+                summary: backend.run_threaded(&streams, sharing, &run),
+                memory: None,
+                shape: None,
+            });
+        }
+    }
+    results.push(BenchResult {
+        backend: "lock-coupling".to_owned(),
+        workload,
+        counts,
+        config: run,
+        summary: threaded::run_lock_coupling(&streams, &run),
+        memory: None,
+        shape: None,
+    });
+    if options.format == Format::Table {
+        println!("{} threads, {} ops each", threads, num_ops);
+    }
+    output::write(&mut io::stdout(), options.format, &results)
+        .expect("failed to write results");
+}
+
+fn sweep(options: &cli::Options, backends: &[Box<dyn Backend>], min: usize, max: usize) {
+    let run = options.run_config();
+    let mut results = Vec::new();
+    for size in sweep::sizes(min, max, options.factor) {
+        let workload = benchmark::Workload::rand_ops(size, options.seed);
+        let ops = workload.ops().unwrap();
+        let counts = benchmark::OpCounts::of(&ops);
+        for backend in backends {
+            eprintln!("{} at size {}", backend.name(), size);
+            results.push(BenchResult {
+                backend: backend.name().to_owned(),
+                workload: workload.clone(),
+                counts,
+                config: run,
+                summary: backend.run(&ops, &run),
+                memory: Some(backend.memory(&ops)),
+                shape: backend.shape(&ops),
+            });
+        }
+    }
+
+    let names: Vec<&str> = backends.iter().map(|b| b.name()).collect();
+    match options.format {
+        Format::Table => sweep::write_table(&mut io::stdout(), &names, &results),
+        format => output::write(&mut io::stdout(), format, &results),
+    }.expect("failed to write results");
+}
+
+fn dot(options: &cli::Options, backends: &[Box<dyn Backend>], num_ops: usize) {
+    let ops = benchmark::Workload::rand_ops(num_ops, options.seed).ops().unwrap();
+    for backend in backends {
+        match backend.dot(&ops, options.annotation) {
+            Some(dot) => print!("// {}\n{}", backend.name(), dot),
+            None => eprintln!("{} doesn't expose its shape", backend.name()),
         };
     }
 }
 
+// returns the exit code: 0 if there were no regressions, 1 if there were, 2 on error or
+// if nothing could be compared
+fn compare(options: &cli::Options, backends: &[Box<dyn Backend>], baseline: &str) -> i32 {
+    let records = fs::read_to_string(baseline)
+        .map_err(|e| format!("failed to read {}: {}", baseline, e))
+        .and_then(|text| output::read_json(&text));
+    let baselines = match records {
+        Ok(records) => compare::baselines(records),
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
 
+    let config = options.compare_config();
+    let report = match compare::compare(&baselines, backends, &config) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let comparisons = report.comparisons;
 
-fn main() {
-    match args().collect::<Vec<String>>().as_slice() {
-        &[_, ref num_ops] => {
-            let num_ops: usize = num_ops.parse()
-                .expect("invalid num ops");
-
-            let ops = benchmark::rand_ops::<i32>(num_ops);
-
-            let bonzai_ms = benchmark::time_ms::<i32, bst::bonzai::BonzaiBst<i32>>(ops.clone());
-            println!("bonzai ms:");
-            eprintln!("{}", bonzai_ms);
-            let boxy_ms = benchmark::time_ms::<i32, bst::boxy::BoxBst<i32>>(ops.clone());
-            println!("boxy ms:");
-            eprintln!("{}", boxy_ms);
+    if !report.unmatched.is_empty() {
+        let names: Vec<&str> = backends.iter().map(|backend| backend.name()).collect();
+        for baseline in &report.unmatched {
+            eprintln!("no backend {:?} to compare {} {} against, running: {}",
+                      baseline.backend, baseline.workload.name, baseline.workload.size,
+                      names.join(", "));
+        }
+    }
+    for baseline in &report.zero_median {
+        eprintln!("the baseline median of {} on {} {} is 0 ns, so no change can be measured \
+                   against it", baseline.backend, baseline.workload.name, baseline.workload.size);
+    }
+    if comparisons.is_empty() {
+        eprintln!("nothing in {} was compared", baseline);
+        return 2;
+    }
+
+    println!("{}", compare::HEADER);
+    for comparison in &comparisons {
+        println!("{}", comparison);
+    }
+    let regressions = comparisons.iter().filter(|c| c.regression).count();
+    if regressions > 0 {
+        eprintln!("{} regression(s) slower by more than {}% at p < {}",
+                  regressions, config.threshold * 100.0, config.alpha);
+        1
+    } else {
+        0
+    }
+}
+
+// returns the exit code: 0 if every backend agreed with the reference, 1 if any didn't, 2 on
+// error
+fn check(options: &cli::Options, seeds: u64, num_ops: usize) -> i32 {
+    let subjects: Vec<check::Subject<i32>> = match options.backends {
+        Some(ref names) => match check::select(names) {
+            Ok(subjects) => subjects,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 2;
+            }
         },
-        _ => {
-            eprintln!("use: ./bonzai-nbst [num_ops]")
+        None => check::backends(),
+    };
+    let reference = check::reference();
+    let names: Vec<&str> = subjects.iter().map(|subject| subject.name).collect();
+    let seeds = options.seed..options.seed + seeds;
+    match check::check_seeds(seeds.clone(), num_ops, 1000, &subjects, &reference) {
+        Ok(()) => {
+            println!("{} agree with {} on seeds {}..{} of {} ops",
+                     names.join(", "), reference.name, seeds.start, seeds.end, num_ops);
+            0
+        },
+        Err(failure) => {
+            eprintln!("{}", failure);
+            1
         }
     }
-}
\ No newline at end of file
+}
+
+// returns the exit code: 0 if every input passed, 1 if any diverged, 2 on error
+fn replay(inputs: &[String]) -> i32 {
+    let mut code = 0;
+    for input in inputs {
+        let data = match fs::read(input) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("failed to read {}: {}", input, e);
+                return 2;
+            }
+        };
+        match check::fuzz::check_input(&data) {
+            Ok(()) => println!("{}: {} ops, ok", input, check::fuzz::decode_ops(&data).len()),
+            Err(divergence) => {
+                eprintln!("{}: {}\n{}", input, divergence, divergence.tree);
+                code = 1;
+            }
+        };
+    }
+    code
+}
@@ -0,0 +1,39 @@
+//! The allocation counts, with `TrackingAlloc` installed as the global allocator as the
+//! benchmark binary installs it. The counts are global, so this is its own test binary, with
+//! everything checked from one test so that nothing else allocates alongside it.
+
+extern crate bonzai_nbst;
+
+use bonzai_nbst::benchmark::Op;
+use bonzai_nbst::benchmark::memory::{self, TrackingAlloc};
+use bonzai_nbst::bst::boxy::BoxBst;
+
+use std::mem;
+use std::ptr;
+
+#[global_allocator]
+static ALLOC: TrackingAlloc = TrackingAlloc;
+
+#[test]
+fn tracking_counts_allocations() {
+    let len = 1 << 20;
+    let (kept, usage) = memory::track(|| {
+        let freed = vec![1u8; len];
+        // read it, so that the allocation can't be optimized away
+        assert_eq!(unsafe { ptr::read_volatile(&freed[len - 1]) }, 1);
+        mem::drop(freed);
+        Vec::<u8>::with_capacity(len / 2)
+    });
+    assert!(usage.allocations >= 2, "{:?}", usage);
+    assert!(usage.bytes_allocated >= len + len / 2, "{:?}", usage);
+    assert!(usage.peak_bytes >= len, "{:?}", usage);
+    // freeing the first vec lowered the live bytes again, leaving the second
+    assert!(usage.live_bytes >= len / 2 && usage.live_bytes < len, "{:?}", usage);
+    mem::drop(kept);
+
+    let ops: Vec<Op<i32>> = (0..1000).map(Op::Insert).collect();
+    let usage = memory::measure::<i32, BoxBst<i32>>(&ops);
+    assert_eq!(usage.elements, 1000);
+    assert!(usage.allocations >= 1000, "{:?}", usage);
+    assert!(usage.live_bytes >= 1000 * mem::size_of::<i32>(), "{:?}", usage);
+}